@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Guest physical addresses and frame numbers.
+//!
+//! The firmware ABI mixes byte addresses and 4 KiB frame numbers, and
+//! converting between them by hand is an easy place to be off by a factor
+//! of 4096. [`Gpa`] and [`Gfn`] keep the two apart; conversions are
+//! explicit and checked.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The number of address bits covered by a page.
+pub const PAGE_SHIFT: u32 = 12;
+
+/// The size of a page in bytes.
+pub const PAGE_SIZE: u64 = 1 << PAGE_SHIFT;
+
+/// The error returned when converting an address that is not page-aligned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Unaligned(pub Gpa);
+
+impl fmt::Display for Unaligned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "address {} is not page-aligned", self.0)
+    }
+}
+
+impl std::error::Error for Unaligned {}
+
+impl Unaligned {
+    /// A stable numeric code for the error, in the range 1000-1099.
+    pub fn code(&self) -> u32 {
+        1000
+    }
+}
+
+/// A guest physical address.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gpa(pub u64);
+
+impl Gpa {
+    /// Whether the address is the start of a page.
+    pub fn is_aligned(self) -> bool {
+        self.page_offset() == 0
+    }
+
+    /// The start of the page containing the address.
+    pub fn align_down(self) -> Self {
+        Self(self.0 & !(PAGE_SIZE - 1))
+    }
+
+    /// The start of the first page at or above the address, unless that
+    /// overflows.
+    pub fn align_up(self) -> Option<Self> {
+        self.0
+            .checked_add(PAGE_SIZE - 1)
+            .map(|a| Self(a).align_down())
+    }
+
+    /// The frame containing the address.
+    pub fn gfn(self) -> Gfn {
+        Gfn(self.0 >> PAGE_SHIFT)
+    }
+
+    /// The offset of the address within its page.
+    pub fn page_offset(self) -> u64 {
+        self.0 % PAGE_SIZE
+    }
+
+    /// The address `bytes` further on, unless that overflows.
+    pub fn checked_add(self, bytes: u64) -> Option<Self> {
+        self.0.checked_add(bytes).map(Self)
+    }
+}
+
+impl fmt::Display for Gpa {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+/// A guest frame number, the index of a 4 KiB guest page.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gfn(pub u64);
+
+impl Gfn {
+    /// The address of the start of the frame, unless it exceeds 64 bits.
+    pub fn gpa(self) -> Option<Gpa> {
+        if self.0 > u64::MAX >> PAGE_SHIFT {
+            return None;
+        }
+
+        Some(Gpa(self.0 << PAGE_SHIFT))
+    }
+
+    /// The frame `pages` further on, unless that overflows.
+    pub fn checked_add(self, pages: u64) -> Option<Self> {
+        self.0.checked_add(pages).map(Self)
+    }
+
+    /// The number of frames from `start` up to this one, unless `start`
+    /// lies after it.
+    pub fn checked_sub(self, start: Gfn) -> Option<u64> {
+        self.0.checked_sub(start.0)
+    }
+}
+
+impl TryFrom<Gpa> for Gfn {
+    type Error = Unaligned;
+
+    /// Converts a page-aligned address.
+    fn try_from(gpa: Gpa) -> Result<Self, Unaligned> {
+        if !gpa.is_aligned() {
+            return Err(Unaligned(gpa));
+        }
+
+        Ok(gpa.gfn())
+    }
+}
+
+impl fmt::Display for Gfn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "gfn {:#x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Gfn::try_from(Gpa(0x5000)), Ok(Gfn(5)));
+        assert_eq!(Gfn::try_from(Gpa(0x5001)), Err(Unaligned(Gpa(0x5001))));
+        assert_eq!(Gpa(0x5fff).gfn(), Gfn(5));
+        assert_eq!(Gfn(5).gpa(), Some(Gpa(0x5000)));
+
+        // 5-level paging addresses are well within range.
+        assert_eq!(Gfn(1 << 45).gpa(), Some(Gpa(1 << 57)));
+        assert_eq!(Gfn(1 << 52).gpa(), None);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Gpa(0x5001).align_down(), Gpa(0x5000));
+        assert_eq!(Gpa(0x5001).align_up(), Some(Gpa(0x6000)));
+        assert_eq!(Gpa(0x5000).align_up(), Some(Gpa(0x5000)));
+        assert_eq!(Gpa(u64::MAX).align_up(), None);
+        assert_eq!(Gpa(0x5123).page_offset(), 0x123);
+        assert_eq!(Gpa(u64::MAX).checked_add(1), None);
+
+        assert_eq!(Gfn(5).checked_add(3), Some(Gfn(8)));
+        assert_eq!(Gfn(8).checked_sub(Gfn(5)), Some(3));
+        assert_eq!(Gfn(5).checked_sub(Gfn(8)), None);
+
+        assert_eq!(Gpa(0x5000).to_string(), "0x5000");
+        assert_eq!(Gfn(5).to_string(), "gfn 0x5");
+    }
+}
@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Appraisal of attestation report claims against a declarative policy.
+//!
+//! An [`AppraisalPolicy`] lists what a relying party is willing to accept.
+//! With the `serde` feature it can be loaded from a configuration file, so
+//! the accepted values can change without recompiling. Appraisal only
+//! looks at the claims; it assumes the report's signature has already been
+//! verified.
+//!
+//! Byte fields are hex-encoded and omitted constraints accept anything:
+//!
+//! ```json
+//! {
+//!     "measurements": ["<96 hex digits>"],
+//!     "min_tcb": { "bootloader": 3, "tee": 0, "snp": 8, "microcode": 115 },
+//!     "forbidden_policy": 524288
+//! }
+//! ```
+
+use crate::launch::IdBlock;
+use crate::report::{AttestationReport, PlatformInfo, TcbVersion};
+
+use std::fmt;
+
+/// Constraints on the claims of an attestation report.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AppraisalPolicy {
+    /// Accepted launch measurements. Any measurement is accepted if empty.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))]
+    pub measurements: Vec<[u8; 48]>,
+
+    /// Minimum guest SVN, rejecting rolled back guest images.
+    pub min_guest_svn: u32,
+
+    /// Minimum SVN of each component of the reported TCB.
+    pub min_tcb: TcbVersion,
+
+    /// Guest policy bits that must be set.
+    pub required_policy: u64,
+
+    /// Guest policy bits that must be clear.
+    pub forbidden_policy: u64,
+
+    /// `PLATFORM_INFO` flags that must be set, e.g. to require TSME.
+    #[cfg_attr(feature = "serde", serde(with = "platform_info"))]
+    pub required_platform: PlatformInfo,
+
+    /// `PLATFORM_INFO` flags that must be clear, e.g. to forbid SMT.
+    #[cfg_attr(feature = "serde", serde(with = "platform_info"))]
+    pub forbidden_platform: PlatformInfo,
+
+    /// Accepted ID key digests. Any ID key is accepted if empty.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))]
+    pub id_key_digests: Vec<[u8; 48]>,
+
+    /// Required `HOST_DATA`, if any.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option"))]
+    pub host_data: Option<[u8; 32]>,
+
+    /// Required `FAMILY_ID`, if any.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option"))]
+    pub family_id: Option<[u8; 16]>,
+
+    /// Required `IMAGE_ID`, if any.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option"))]
+    pub image_id: Option<[u8; 16]>,
+}
+
+/// `PLATFORM_INFO` flags as their numeric value.
+#[cfg(feature = "serde")]
+mod platform_info {
+    use super::PlatformInfo;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        flags: &PlatformInfo,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(flags.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PlatformInfo, D::Error> {
+        let bits = u64::deserialize(deserializer)?;
+        PlatformInfo::from_bits(bits)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown platform flags {:#x}", bits)))
+    }
+}
+
+/// A single violated constraint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The measurement is not one of the accepted measurements.
+    Measurement,
+
+    /// The guest SVN is below the minimum.
+    GuestSvn {
+        /// The reported guest SVN.
+        reported: u32,
+        /// The minimum guest SVN.
+        minimum: u32,
+    },
+
+    /// A component of the reported TCB is below its minimum.
+    Tcb {
+        /// The name of the component.
+        component: &'static str,
+        /// The reported SVN of the component.
+        reported: u8,
+        /// The minimum SVN of the component.
+        minimum: u8,
+    },
+
+    /// These required guest policy bits are clear.
+    PolicyMissing(u64),
+
+    /// These forbidden guest policy bits are set.
+    PolicyForbidden(u64),
+
+    /// These required platform flags are clear.
+    PlatformMissing(PlatformInfo),
+
+    /// These forbidden platform flags are set.
+    PlatformForbidden(PlatformInfo),
+
+    /// The ID key digest is not one of the accepted digests.
+    IdKeyDigest,
+
+    /// `HOST_DATA` differs from the required value.
+    HostData,
+
+    /// `FAMILY_ID` differs from the required value.
+    FamilyId,
+
+    /// `IMAGE_ID` differs from the required value.
+    ImageId,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Measurement => write!(f, "measurement not accepted"),
+            Failure::GuestSvn { reported, minimum } => write!(
+                f,
+                "guest SVN {} is below the minimum of {}",
+                reported, minimum
+            ),
+            Failure::Tcb {
+                component,
+                reported,
+                minimum,
+            } => write!(
+                f,
+                "{} SVN {} is below the minimum of {}",
+                component, reported, minimum
+            ),
+            Failure::PolicyMissing(bits) => {
+                write!(f, "required policy bits {:#x} are clear", bits)
+            }
+            Failure::PolicyForbidden(bits) => {
+                write!(f, "forbidden policy bits {:#x} are set", bits)
+            }
+            Failure::PlatformMissing(flags) => {
+                write!(f, "required platform flags {:?} are clear", flags)
+            }
+            Failure::PlatformForbidden(flags) => {
+                write!(f, "forbidden platform flags {:?} are set", flags)
+            }
+            Failure::IdKeyDigest => write!(f, "ID key not accepted"),
+            Failure::HostData => write!(f, "host data mismatch"),
+            Failure::FamilyId => write!(f, "family ID mismatch"),
+            Failure::ImageId => write!(f, "image ID mismatch"),
+        }
+    }
+}
+
+impl Failure {
+    /// A stable numeric code for the failure, in the range 700-799.
+    pub fn code(&self) -> u32 {
+        match self {
+            Failure::Measurement => 700,
+            Failure::GuestSvn { .. } => 701,
+            Failure::Tcb { .. } => 702,
+            Failure::PolicyMissing(_) => 703,
+            Failure::PolicyForbidden(_) => 704,
+            Failure::PlatformMissing(_) => 705,
+            Failure::PlatformForbidden(_) => 706,
+            Failure::IdKeyDigest => 707,
+            Failure::HostData => 708,
+            Failure::FamilyId => 709,
+            Failure::ImageId => 710,
+        }
+    }
+}
+
+/// The verdict of appraising a report.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Appraisal {
+    /// Every constraint the report violated, in policy order.
+    pub failures: Vec<Failure>,
+}
+
+impl Appraisal {
+    /// Whether the report satisfied every constraint.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl AppraisalPolicy {
+    /// Derives a policy requiring exactly what the ID block asserts.
+    ///
+    /// The launch digest becomes the only accepted measurement, the guest
+    /// policy must match exactly, the guest SVN is the minimum, and the
+    /// family and image IDs are required. This keeps the values defined at
+    /// launch time in one place instead of repeating them for
+    /// verification. Set [`host_data`](Self::host_data) as well if the
+    /// guest was launched with it.
+    pub fn from_id_block(block: &IdBlock) -> Self {
+        Self {
+            measurements: vec![block.ld],
+            min_guest_svn: block.guest_svn,
+            required_policy: block.policy,
+            forbidden_policy: !block.policy,
+            family_id: Some(block.family_id),
+            image_id: Some(block.image_id),
+            ..Default::default()
+        }
+    }
+
+    /// Evaluates the claims of a report against the policy.
+    pub fn appraise(&self, report: &AttestationReport) -> Appraisal {
+        let mut failures = Vec::new();
+
+        if !self.measurements.is_empty() && !self.measurements.contains(&report.measurement) {
+            failures.push(Failure::Measurement);
+        }
+
+        if report.guest_svn < self.min_guest_svn {
+            failures.push(Failure::GuestSvn {
+                reported: report.guest_svn,
+                minimum: self.min_guest_svn,
+            });
+        }
+
+        let tcb = report.reported_tcb;
+        let components = [
+            ("bootloader", tcb.bootloader, self.min_tcb.bootloader),
+            ("tee", tcb.tee, self.min_tcb.tee),
+            ("snp", tcb.snp, self.min_tcb.snp),
+            ("microcode", tcb.microcode, self.min_tcb.microcode),
+        ];
+        for &(component, reported, minimum) in components.iter() {
+            if reported < minimum {
+                failures.push(Failure::Tcb {
+                    component,
+                    reported,
+                    minimum,
+                });
+            }
+        }
+
+        let missing = self.required_policy & !report.policy;
+        if missing != 0 {
+            failures.push(Failure::PolicyMissing(missing));
+        }
+
+        let forbidden = self.forbidden_policy & report.policy;
+        if forbidden != 0 {
+            failures.push(Failure::PolicyForbidden(forbidden));
+        }
+
+        let platform = report.platform_flags();
+        let missing = self.required_platform - platform;
+        if !missing.is_empty() {
+            failures.push(Failure::PlatformMissing(missing));
+        }
+
+        let forbidden = self.forbidden_platform & platform;
+        if !forbidden.is_empty() {
+            failures.push(Failure::PlatformForbidden(forbidden));
+        }
+
+        if !self.id_key_digests.is_empty() && !self.id_key_digests.contains(&report.id_key_digest) {
+            failures.push(Failure::IdKeyDigest);
+        }
+
+        if matches!(self.host_data, Some(v) if v != report.host_data) {
+            failures.push(Failure::HostData);
+        }
+
+        if matches!(self.family_id, Some(v) if v != report.family_id) {
+            failures.push(Failure::FamilyId);
+        }
+
+        if matches!(self.image_id, Some(v) if v != report.image_id) {
+            failures.push(Failure::ImageId);
+        }
+
+        Appraisal { failures }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> AttestationReport {
+        AttestationReport::from_bytes(&crate::report::tests::example()).unwrap()
+    }
+
+    #[test]
+    fn empty_policy_passes() {
+        assert!(AppraisalPolicy::default().appraise(&report()).passed());
+    }
+
+    #[test]
+    fn failures() {
+        let policy = AppraisalPolicy {
+            measurements: vec![[0; 48]],
+            min_guest_svn: 8,
+            min_tcb: TcbVersion {
+                bootloader: 2,
+                tee: 0,
+                snp: 7,
+                microcode: 100,
+            },
+            required_policy: 1 << 19,
+            forbidden_policy: 1 << 16,
+            id_key_digests: vec![[0x66; 48]],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.appraise(&report()).failures,
+            vec![
+                Failure::Measurement,
+                Failure::GuestSvn {
+                    reported: 7,
+                    minimum: 8
+                },
+                Failure::Tcb {
+                    component: "snp",
+                    reported: 6,
+                    minimum: 7
+                },
+                Failure::PolicyMissing(1 << 19),
+                Failure::PolicyForbidden(1 << 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepted() {
+        let policy = AppraisalPolicy {
+            measurements: vec![[0; 48], [0x44; 48]],
+            min_guest_svn: 7,
+            required_policy: 1 << 17,
+            forbidden_policy: 1 << 19,
+            id_key_digests: vec![[0x66; 48]],
+            ..Default::default()
+        };
+
+        assert!(policy.appraise(&report()).passed());
+    }
+
+    #[test]
+    fn platform() {
+        let mut policy = AppraisalPolicy {
+            required_platform: PlatformInfo::TSME_EN,
+            forbidden_platform: PlatformInfo::RAPL_DIS,
+            ..Default::default()
+        };
+        assert!(policy.appraise(&report()).passed());
+
+        policy.required_platform |= PlatformInfo::ECC_EN;
+        policy.forbidden_platform |= PlatformInfo::SMT_EN;
+        assert_eq!(
+            policy.appraise(&report()).failures,
+            vec![
+                Failure::PlatformMissing(PlatformInfo::ECC_EN),
+                Failure::PlatformForbidden(PlatformInfo::SMT_EN),
+            ]
+        );
+    }
+
+    #[test]
+    fn id_block() {
+        let block = IdBlock::new([0x44; 48], 0x3_0000)
+            .with_family_id([0x11; 16])
+            .with_image_id([0x22; 16])
+            .with_guest_svn(7);
+
+        let mut policy = AppraisalPolicy::from_id_block(&block);
+        assert!(policy.appraise(&report()).passed());
+
+        policy.host_data = Some([0x55; 32]);
+        assert!(policy.appraise(&report()).passed());
+
+        let block = block.with_image_id([0x23; 16]).with_guest_svn(8);
+        let mut policy = AppraisalPolicy::from_id_block(&block);
+        policy.host_data = Some([0; 32]);
+        assert_eq!(
+            policy.appraise(&report()).failures,
+            vec![
+                Failure::GuestSvn {
+                    reported: 7,
+                    minimum: 8
+                },
+                Failure::HostData,
+                Failure::ImageId,
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load() {
+        let json = format!(
+            r#"{{ "measurements": ["{}"], "min_tcb": {{ "bootloader": 1, "tee": 0, "snp": 6, "microcode": 9 }}, "forbidden_policy": 524288 }}"#,
+            "44".repeat(48)
+        );
+
+        let policy: AppraisalPolicy = serde_json::from_str(&json).unwrap();
+        assert_eq!(policy.measurements, vec![[0x44; 48]]);
+        assert_eq!(policy.min_tcb.microcode, 9);
+        assert_eq!(policy.forbidden_policy, 1 << 19);
+        assert!(policy.id_key_digests.is_empty());
+        assert_eq!(policy.host_data, None);
+        assert!(policy.appraise(&report()).passed());
+
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(
+            serde_json::from_str::<AppraisalPolicy>(&json).unwrap(),
+            policy
+        );
+
+        assert!(serde_json::from_str::<AppraisalPolicy>(r#"{ "measurements": ["00"] }"#).is_err());
+        assert!(serde_json::from_str::<AppraisalPolicy>(r#"{ "unknown": 1 }"#).is_err());
+        let policy: AppraisalPolicy =
+            serde_json::from_str(r#"{ "required_platform": 2 }"#).unwrap();
+        assert_eq!(policy.required_platform, PlatformInfo::TSME_EN);
+        assert!(serde_json::from_str::<AppraisalPolicy>(r#"{ "required_platform": 64 }"#).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The certificate table returned with extended guest requests.
+//!
+//! The table is a list of entries, each made up of a GUID identifying the
+//! certificate, and the offset and length of the certificate within the
+//! same buffer. An entry of all zeroes terminates the list (see
+//! "SNP Extended Guest Request" in the GHCB specification).
+
+use crate::parse::{Error, ErrorKind, Reader};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A GUID identifying an entry in a [`CertTable`].
+///
+/// The bytes are kept in the order produced by `uuid_parse(3)`, which is
+/// the order AMD's tooling uses when assembling certificate tables.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// The Versioned Chip Endorsement Key certificate.
+    pub const VCEK: Guid = Guid([
+        0x63, 0xda, 0x75, 0x8d, 0xe6, 0x64, 0x45, 0x64, 0xad, 0xc5, 0xf4, 0xb9, 0x3b, 0xe8, 0xac,
+        0xcd,
+    ]);
+
+    /// The Versioned Loaded Endorsement Key certificate.
+    pub const VLEK: Guid = Guid([
+        0xa8, 0x07, 0x4b, 0xc2, 0xa2, 0x5a, 0x48, 0x3e, 0xaa, 0xe6, 0x39, 0xc0, 0x45, 0xa0, 0xb8,
+        0xa1,
+    ]);
+
+    /// The AMD SEV Signing Key certificate.
+    pub const ASK: Guid = Guid([
+        0x4a, 0xb7, 0xb3, 0x79, 0xbb, 0xac, 0x4f, 0xe4, 0xa0, 0x2f, 0x05, 0xae, 0xf3, 0x27, 0xc7,
+        0x82,
+    ]);
+
+    /// The AMD Root Key certificate.
+    pub const ARK: Guid = Guid([
+        0xc0, 0xb4, 0x06, 0xa4, 0xa8, 0x03, 0x49, 0x52, 0x97, 0x43, 0x3f, 0xb6, 0x01, 0x4c, 0xd0,
+        0xae,
+    ]);
+
+    /// The all-zero GUID marking the end of the table.
+    pub const NULL: Guid = Guid([0; 16]);
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if let 4 | 6 | 8 | 10 = i {
+                f.write_str("-")?;
+            }
+
+            write!(f, "{:02x}", b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The error returned when a string is not a hyphenated GUID.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseGuidError;
+
+impl fmt::Display for ParseGuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid GUID")
+    }
+}
+
+impl std::error::Error for ParseGuidError {}
+
+impl ParseGuidError {
+    /// A stable numeric code for the error, in the range 300-399.
+    pub fn code(&self) -> u32 {
+        310
+    }
+}
+
+impl FromStr for Guid {
+    type Err = ParseGuidError;
+
+    /// Parses the hyphenated form produced by [`Display`](fmt::Display).
+    fn from_str(s: &str) -> Result<Self, ParseGuidError> {
+        let s = s.as_bytes();
+        if s.len() != 36 || [8, 13, 18, 23].iter().any(|&i| s[i] != b'-') {
+            return Err(ParseGuidError);
+        }
+
+        let mut digits = s.iter().filter(|c| **c != b'-');
+        let mut guid = [0u8; 16];
+        for byte in guid.iter_mut() {
+            let mut nibble = || {
+                digits
+                    .next()
+                    .and_then(|c| (*c as char).to_digit(16))
+                    .ok_or(ParseGuidError)
+            };
+            *byte = (nibble()? << 4 | nibble()?) as u8;
+        }
+
+        Ok(Guid(guid))
+    }
+}
+
+/// A certificate stored in a [`CertTable`].
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertTableEntry {
+    /// Identifies the kind of certificate.
+    pub guid: Guid,
+
+    /// The certificate, usually DER encoded.
+    pub data: Vec<u8>,
+}
+
+/// Errors produced when encoding a certificate table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// An entry uses the all-zero GUID reserved for the terminator.
+    NullGuid,
+
+    /// Two entries use the same GUID.
+    DuplicateGuid(Guid),
+
+    /// The table does not fit the 32-bit offsets and lengths.
+    TooLarge,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NullGuid => write!(f, "entry uses the terminator GUID"),
+            BuildError::DuplicateGuid(guid) => write!(f, "duplicate entry {}", guid),
+            BuildError::TooLarge => write!(f, "certificate table too large"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl BuildError {
+    /// A stable numeric code for the error, in the range 300-399.
+    pub fn code(&self) -> u32 {
+        match self {
+            BuildError::NullGuid => 300,
+            BuildError::DuplicateGuid(_) => 301,
+            BuildError::TooLarge => 302,
+        }
+    }
+}
+
+/// A decoded certificate table.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertTable {
+    /// The entries, in the order they appear in the table.
+    pub entries: Vec<CertTableEntry>,
+}
+
+impl CertTable {
+    /// Size of a single encoded table entry.
+    pub const ENTRY_SIZE: usize = 24;
+
+    /// The encoded table is padded to a multiple of this size, as the
+    /// kernel only accepts whole pages of certificates.
+    pub const ALIGNMENT: usize = 4096;
+
+    /// Returns a builder for assembling a table.
+    pub fn builder() -> CertTableBuilder {
+        CertTableBuilder::default()
+    }
+
+    /// Returns the data of the first entry with the given GUID.
+    pub fn get(&self, guid: &Guid) -> Option<&[u8]> {
+        self.entries
+            .iter()
+            .find(|e| e.guid == *guid)
+            .map(|e| &e.data[..])
+    }
+
+    /// Decodes a certificate table.
+    ///
+    /// The buffer may extend past the last certificate, as the host
+    /// usually hands out page-sized buffers.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(bytes);
+        let mut entries = Vec::new();
+
+        loop {
+            let start = reader.offset();
+            let guid = Guid(reader.array()?);
+            let offset = reader.u32()? as usize;
+            let length = reader.u32()? as usize;
+
+            if guid == Guid::NULL {
+                if offset != 0 || length != 0 {
+                    return Err(Error::new(start, ErrorKind::InvalidValue));
+                }
+
+                break;
+            }
+
+            let data = offset
+                .checked_add(length)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or_else(|| Error::new(start + 16, ErrorKind::OutOfBounds))?;
+
+            entries.push(CertTableEntry {
+                guid,
+                data: data.to_vec(),
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Encodes the table, followed by the certificates and zero padding.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BuildError> {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.guid == Guid::NULL {
+                return Err(BuildError::NullGuid);
+            }
+
+            if self.entries[..i].iter().any(|e| e.guid == entry.guid) {
+                return Err(BuildError::DuplicateGuid(entry.guid));
+            }
+        }
+
+        let header = (self.entries.len() + 1) * Self::ENTRY_SIZE;
+        let size = self
+            .entries
+            .iter()
+            .try_fold(header, |size, e| size.checked_add(e.data.len()))
+            .filter(|size| *size <= u32::MAX as usize)
+            .ok_or(BuildError::TooLarge)?;
+
+        let mut bytes = Vec::with_capacity(size);
+        let mut offset = header;
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.guid.0);
+            bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+            bytes.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            offset += entry.data.len();
+        }
+        bytes.extend_from_slice(&[0; Self::ENTRY_SIZE]);
+
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.data);
+        }
+
+        bytes.resize(
+            size + (Self::ALIGNMENT - size % Self::ALIGNMENT) % Self::ALIGNMENT,
+            0,
+        );
+        Ok(bytes)
+    }
+}
+
+/// Assembles a [`CertTable`] for `SNP_SET_EXT_CONFIG` or for tests.
+///
+/// ```
+/// use snp::certs::{CertTable, Guid};
+///
+/// # let (vcek, ask, ark) = (vec![1], vec![2], vec![3]);
+/// let bytes = CertTable::builder().vcek(vcek).ask(ask).ark(ark).build().unwrap();
+///
+/// let table = CertTable::from_bytes(&bytes).unwrap();
+/// assert_eq!(table.get(&Guid::ASK), Some(&[2][..]));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CertTableBuilder {
+    table: CertTable,
+}
+
+impl CertTableBuilder {
+    /// Adds a certificate with an arbitrary GUID.
+    pub fn entry(mut self, guid: Guid, data: impl Into<Vec<u8>>) -> Self {
+        self.table.entries.push(CertTableEntry {
+            guid,
+            data: data.into(),
+        });
+        self
+    }
+
+    /// Adds the DER-encoded VCEK certificate.
+    pub fn vcek(self, der: impl Into<Vec<u8>>) -> Self {
+        self.entry(Guid::VCEK, der)
+    }
+
+    /// Adds the DER-encoded VLEK certificate.
+    pub fn vlek(self, der: impl Into<Vec<u8>>) -> Self {
+        self.entry(Guid::VLEK, der)
+    }
+
+    /// Adds the DER-encoded ASK certificate.
+    pub fn ask(self, der: impl Into<Vec<u8>>) -> Self {
+        self.entry(Guid::ASK, der)
+    }
+
+    /// Adds the DER-encoded ARK certificate.
+    pub fn ark(self, der: impl Into<Vec<u8>>) -> Self {
+        self.entry(Guid::ARK, der)
+    }
+
+    /// Returns the table assembled so far.
+    pub fn table(self) -> CertTable {
+        self.table
+    }
+
+    /// Encodes the table; see [`CertTable::to_bytes`].
+    pub fn build(self) -> Result<Vec<u8>, BuildError> {
+        self.table.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(bytes: &mut Vec<u8>, guid: Guid, offset: u32, length: u32) {
+        bytes.extend_from_slice(&guid.0);
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&length.to_le_bytes());
+    }
+
+    fn example() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        entry(&mut bytes, Guid::VCEK, 72, 3);
+        entry(&mut bytes, Guid::ASK, 75, 2);
+        entry(&mut bytes, Guid::NULL, 0, 0);
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5]);
+        bytes
+    }
+
+    #[test]
+    fn guid_display() {
+        assert_eq!(
+            Guid::VCEK.to_string(),
+            "63da758d-e664-4564-adc5-f4b93be8accd"
+        );
+        assert_eq!(
+            Guid::ARK.to_string(),
+            "c0b406a4-a803-4952-9743-3fb6014cd0ae"
+        );
+    }
+
+    #[test]
+    fn guid_parse() {
+        assert_eq!(
+            "63da758d-e664-4564-adc5-f4b93be8accd".parse(),
+            Ok(Guid::VCEK)
+        );
+        assert_eq!(
+            "C0B406A4-A803-4952-9743-3FB6014CD0AE".parse(),
+            Ok(Guid::ARK)
+        );
+
+        for s in [
+            "",
+            "63da758de664-4564-adc5-f4b93be8accd0",
+            "63da758d-e664-4564-adc5-f4b93be8accz",
+            "63da758d-e664-4564-adc5-f4b93be8-ccd",
+            "+3da758d-e664-4564-adc5-f4b93be8accd",
+        ]
+        .iter()
+        {
+            assert_eq!(s.parse::<Guid>(), Err(ParseGuidError));
+        }
+    }
+
+    #[test]
+    fn decode() {
+        let table = CertTable::from_bytes(&example()).unwrap();
+
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(table.get(&Guid::VCEK), Some(&[1, 2, 3][..]));
+        assert_eq!(table.get(&Guid::ASK), Some(&[4, 5][..]));
+        assert_eq!(table.get(&Guid::ARK), None);
+    }
+
+    #[test]
+    fn empty() {
+        let mut bytes = Vec::new();
+        entry(&mut bytes, Guid::NULL, 0, 0);
+
+        assert_eq!(CertTable::from_bytes(&bytes), Ok(CertTable::default()));
+    }
+
+    #[test]
+    fn missing_terminator() {
+        let mut bytes = Vec::new();
+        entry(&mut bytes, Guid::VCEK, 0, 4);
+
+        let err = CertTable::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 24);
+        assert_eq!(err.kind(), ErrorKind::Truncated { needed: 16 });
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut bytes = example();
+        bytes[24 + 20..24 + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let err = CertTable::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 24 + 16);
+        assert_eq!(err.kind(), ErrorKind::OutOfBounds);
+    }
+
+    #[test]
+    fn build() {
+        let bytes = CertTable::builder()
+            .vcek(vec![1, 2, 3])
+            .ask(vec![4, 5])
+            .build()
+            .unwrap();
+
+        assert_eq!(bytes.len(), CertTable::ALIGNMENT);
+        assert_eq!(&bytes[..77], &example()[..]);
+        assert!(bytes[77..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn build_roundtrip() {
+        let builder = CertTable::builder()
+            .vlek(vec![7; 1000])
+            .ask(vec![8; 2000])
+            .ark(vec![9; 3000]);
+        let table = builder.clone().table();
+        let bytes = builder.build().unwrap();
+
+        assert_eq!(bytes.len(), 2 * CertTable::ALIGNMENT);
+        assert_eq!(CertTable::from_bytes(&bytes), Ok(table));
+    }
+
+    #[test]
+    fn build_errors() {
+        assert_eq!(
+            CertTable::builder().vcek(vec![1]).vcek(vec![2]).build(),
+            Err(BuildError::DuplicateGuid(Guid::VCEK))
+        );
+        assert_eq!(
+            CertTable::builder().entry(Guid::NULL, vec![1]).build(),
+            Err(BuildError::NullGuid)
+        );
+    }
+
+    #[test]
+    fn invalid_terminator() {
+        let mut bytes = example();
+        bytes[48 + 16] = 1;
+
+        let err = CertTable::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 48);
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+    }
+}
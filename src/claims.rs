@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Entity Attestation Token (EAT) style claims for attestation reports.
+//!
+//! [`Claims`] maps the fields of a report onto a flat set of claims so
+//! that SNP evidence can be carried in JWT or CWT based authorization
+//! systems. Claims defined by EAT (RFC 9711) use their registered names;
+//! SNP specific claims are prefixed with `snp_`. The names in [`name`]
+//! are stable: fields may be added, but existing names never change
+//! meaning.
+//!
+//! With the `serde` feature, [`Claims`] serializes to a map keyed by those
+//! names and can be fed to any JSON or CBOR serializer. Byte fields are
+//! hex-encoded.
+//!
+//! Claims are only as trustworthy as the report they came from; the
+//! report's signature must be verified before mapping it.
+
+use crate::report::{AttestationReport, SigningKey, TcbVersion};
+
+/// The registered claim names.
+pub mod name {
+    /// EAT nonce, taken from `REPORT_DATA`.
+    pub const NONCE: &str = "eat_nonce";
+
+    /// EAT universal entity ID, derived from `CHIP_ID` when requested with
+    /// [`Claims::with_ueid`](super::Claims::with_ueid).
+    pub const UEID: &str = "ueid";
+
+    /// EAT debug status, derived from the guest policy's `DEBUG` bit.
+    pub const DBGSTAT: &str = "dbgstat";
+
+    /// The launch measurement.
+    pub const MEASUREMENT: &str = "snp_measurement";
+
+    /// `HOST_DATA` supplied at launch.
+    pub const HOST_DATA: &str = "snp_host_data";
+
+    /// The guest policy.
+    pub const POLICY: &str = "snp_policy";
+
+    /// The guest SVN.
+    pub const GUEST_SVN: &str = "snp_guest_svn";
+
+    /// The VMPL that requested the report.
+    pub const VMPL: &str = "snp_vmpl";
+
+    /// `FAMILY_ID` from the ID block.
+    pub const FAMILY_ID: &str = "snp_family_id";
+
+    /// `IMAGE_ID` from the ID block.
+    pub const IMAGE_ID: &str = "snp_image_id";
+
+    /// Digest of the ID key.
+    pub const ID_KEY_DIGEST: &str = "snp_id_key_digest";
+
+    /// Digest of the author key, when present.
+    pub const AUTHOR_KEY_DIGEST: &str = "snp_author_key_digest";
+
+    /// The reported TCB.
+    pub const REPORTED_TCB: &str = "snp_reported_tcb";
+
+    /// `PLATFORM_INFO`.
+    pub const PLATFORM_INFO: &str = "snp_platform_info";
+
+    /// The key that signed the report: `vcek`, `vlek`, `none`, or
+    /// `reserved` for encodings the specification reserves.
+    pub const SIGNING_KEY: &str = "snp_signing_key";
+}
+
+/// Debug status as defined by EAT.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DebugStatus {
+    /// Debugging is enabled.
+    Enabled,
+
+    /// Debugging is disabled and cannot be enabled for this guest.
+    DisabledPermanently,
+}
+
+impl DebugStatus {
+    /// The EAT claim value.
+    pub fn value(self) -> u8 {
+        match self {
+            DebugStatus::Enabled => 0,
+            DebugStatus::DisabledPermanently => 3,
+        }
+    }
+}
+
+/// The claims of an attestation report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Claims {
+    /// See [`name::NONCE`].
+    pub nonce: [u8; 64],
+
+    /// See [`name::UEID`]. Not set by the conversion from a report.
+    pub ueid: Option<[u8; 33]>,
+
+    /// See [`name::DBGSTAT`].
+    pub dbgstat: DebugStatus,
+
+    /// See [`name::MEASUREMENT`].
+    pub measurement: [u8; 48],
+
+    /// See [`name::HOST_DATA`].
+    pub host_data: [u8; 32],
+
+    /// See [`name::POLICY`].
+    pub policy: u64,
+
+    /// See [`name::GUEST_SVN`].
+    pub guest_svn: u32,
+
+    /// See [`name::VMPL`].
+    pub vmpl: u32,
+
+    /// See [`name::FAMILY_ID`].
+    pub family_id: [u8; 16],
+
+    /// See [`name::IMAGE_ID`].
+    pub image_id: [u8; 16],
+
+    /// See [`name::ID_KEY_DIGEST`].
+    pub id_key_digest: [u8; 48],
+
+    /// See [`name::AUTHOR_KEY_DIGEST`].
+    pub author_key_digest: Option<[u8; 48]>,
+
+    /// See [`name::REPORTED_TCB`].
+    pub reported_tcb: TcbVersion,
+
+    /// See [`name::PLATFORM_INFO`].
+    pub platform_info: u64,
+
+    /// See [`name::SIGNING_KEY`].
+    pub signing_key: SigningKey,
+}
+
+impl From<&AttestationReport> for Claims {
+    fn from(report: &AttestationReport) -> Self {
+        let dbgstat = if report.policy & (1 << crate::spec::policy::DEBUG) != 0 {
+            DebugStatus::Enabled
+        } else {
+            DebugStatus::DisabledPermanently
+        };
+
+        Self {
+            nonce: report.report_data,
+            ueid: None,
+            dbgstat,
+            measurement: report.measurement,
+            host_data: report.host_data,
+            policy: report.policy,
+            guest_svn: report.guest_svn,
+            vmpl: report.vmpl,
+            family_id: report.family_id,
+            image_id: report.image_id,
+            id_key_digest: report.id_key_digest,
+            author_key_digest: if report.author_key_en() {
+                Some(report.author_key_digest)
+            } else {
+                None
+            },
+            reported_tcb: report.reported_tcb,
+            platform_info: report.platform_info,
+            signing_key: report.signing_key(),
+        }
+    }
+}
+
+impl Claims {
+    /// The UEID type of random identifiers, defined by EAT.
+    pub const UEID_TYPE_RAND: u8 = 0x01;
+
+    /// Sets a UEID identifying the report's chip, unless its ID is masked.
+    ///
+    /// The raw `CHIP_ID` is a hardware identifier and no valid UEID, so
+    /// the UEID is the EAT type byte for random IDs followed by 32 bytes
+    /// of [`AttestationReport::chip_id_hmac`] under `key`. It is stable
+    /// per chip and key, but cannot be linked to the chip ID without the
+    /// key.
+    pub fn with_ueid(mut self, report: &AttestationReport, key: &[u8]) -> Self {
+        self.ueid = report.chip_id_hmac(key).map(|mac| {
+            let mut ueid = [0u8; 33];
+            ueid[0] = Self::UEID_TYPE_RAND;
+            ueid[1..].copy_from_slice(&mac[..32]);
+            ueid
+        });
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Claims {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use crate::hex::encode;
+        use serde::ser::SerializeMap;
+
+        let signing_key = match self.signing_key {
+            SigningKey::Vcek => "vcek",
+            SigningKey::Vlek => "vlek",
+            SigningKey::None => "none",
+            SigningKey::Reserved(_) => "reserved",
+        };
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry(name::NONCE, &encode(&self.nonce))?;
+        if let Some(ueid) = &self.ueid {
+            map.serialize_entry(name::UEID, &encode(ueid))?;
+        }
+        map.serialize_entry(name::DBGSTAT, &self.dbgstat.value())?;
+        map.serialize_entry(name::MEASUREMENT, &encode(&self.measurement))?;
+        map.serialize_entry(name::HOST_DATA, &encode(&self.host_data))?;
+        map.serialize_entry(name::POLICY, &self.policy)?;
+        map.serialize_entry(name::GUEST_SVN, &self.guest_svn)?;
+        map.serialize_entry(name::VMPL, &self.vmpl)?;
+        map.serialize_entry(name::FAMILY_ID, &encode(&self.family_id))?;
+        map.serialize_entry(name::IMAGE_ID, &encode(&self.image_id))?;
+        map.serialize_entry(name::ID_KEY_DIGEST, &encode(&self.id_key_digest))?;
+        if let Some(digest) = &self.author_key_digest {
+            map.serialize_entry(name::AUTHOR_KEY_DIGEST, &encode(digest))?;
+        }
+        map.serialize_entry(name::REPORTED_TCB, &self.reported_tcb)?;
+        map.serialize_entry(name::PLATFORM_INFO, &self.platform_info)?;
+        map.serialize_entry(name::SIGNING_KEY, signing_key)?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> AttestationReport {
+        AttestationReport::from_bytes(&crate::report::tests::example()).unwrap()
+    }
+
+    #[test]
+    fn map() {
+        let mut report = report();
+        let claims = Claims::from(&report);
+
+        assert_eq!(claims.nonce, [0x33; 64]);
+        assert_eq!(claims.ueid, None);
+        assert_eq!(claims.dbgstat, DebugStatus::DisabledPermanently);
+        assert_eq!(claims.author_key_digest, Some([0x77; 48]));
+        assert_eq!(claims.signing_key, SigningKey::Vlek);
+
+        report.policy |= 1 << 19;
+        report.key_info = 0;
+        report.chip_id = [0; 64];
+        let claims = Claims::from(&report);
+        assert_eq!(claims.ueid, None);
+        assert_eq!(claims.dbgstat, DebugStatus::Enabled);
+        assert_eq!(claims.author_key_digest, None);
+        assert_eq!(claims.signing_key, SigningKey::Vcek);
+        assert_eq!(claims.with_ueid(&report, b"key").ueid, None);
+    }
+
+    #[test]
+    fn ueid() {
+        let report = report();
+        let ueid = Claims::from(&report)
+            .with_ueid(&report, b"key")
+            .ueid
+            .unwrap();
+
+        assert_eq!(ueid[0], Claims::UEID_TYPE_RAND);
+        assert_eq!(ueid[1..], report.chip_id_hmac(b"key").unwrap()[..32]);
+        assert!(!ueid.windows(4).any(|w| w == [0xaa; 4]));
+
+        let other = Claims::from(&report).with_ueid(&report, b"other key");
+        assert_ne!(other.ueid, Some(ueid));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let mut report = report();
+        report.key_info = 0b11100;
+        report.chip_id = [0; 64];
+
+        let json = serde_json::to_value(Claims::from(&report)).unwrap();
+        let map = json.as_object().unwrap();
+
+        assert_eq!(map[name::NONCE], "33".repeat(64));
+        assert_eq!(map[name::DBGSTAT], 3);
+        assert_eq!(map[name::POLICY], 0x30000);
+        assert_eq!(map[name::REPORTED_TCB]["microcode"], 100);
+        assert_eq!(map[name::SIGNING_KEY], "none");
+        assert!(!map.contains_key(name::UEID));
+        assert!(!map.contains_key(name::AUTHOR_KEY_DIGEST));
+
+        report.key_info = 0b10100;
+        let json = serde_json::to_value(Claims::from(&report)).unwrap();
+        assert_eq!(json[name::SIGNING_KEY], "reserved");
+    }
+}
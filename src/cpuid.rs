@@ -0,0 +1,439 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The CPUID page installed by the firmware at launch.
+//!
+//! The hypervisor proposes the CPUID results of the guest, and the
+//! firmware checks them against the platform's capabilities before
+//! placing them in the CPUID page. That check is what protects the guest
+//! from spoofed results; [`CpuidPage::compare`] only reports where the
+//! page and a second source of CPUID results disagree.
+//!
+//! The comparison is only as independent as that second source. In an
+//! SNP guest, `CPUID` traps to the kernel's #VC handler, and once a CPUID
+//! page is installed Linux answers the trap from this very page. Comparing
+//! the page with `CPUID` executed in such a guest therefore mostly
+//! compares the page with itself: it catches a page that was read from the
+//! wrong place or decoded wrongly, but not a hypervisor that lies about
+//! the results. Detecting that needs results the hypervisor cannot
+//! influence, e.g. reference values recorded for the expected platform.
+//!
+//! Some results legitimately differ between two sources, e.g. the APIC ID
+//! in leaf 1 or the `OSXSAVE` bit, so callers will usually ignore
+//! mismatches in fields they know to be dynamic.
+//!
+//! The page lives at the guest physical address its launch layout gave to
+//! the page of type [`CPUID`](crate::spec::page_type::CPUID). This module
+//! cannot locate it on its own: [`CpuidPage::read_at`] reads it from a
+//! caller-provided view of guest memory, such as `/dev/mem` or a mapping
+//! set up by the firmware, and [`CpuidPage::read_from`] from any reader
+//! positioned at the page.
+
+use crate::addr::{Gpa, Unaligned};
+use crate::parse::{Error, ErrorKind, Reader};
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Errors produced while reading the CPUID page from guest memory.
+#[derive(Debug)]
+pub enum ReadError {
+    /// The page address is not page-aligned.
+    Unaligned(Unaligned),
+
+    /// Reading the page failed.
+    Io(io::Error),
+
+    /// The page could not be decoded.
+    Parse(Error),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Unaligned(e) => write!(f, "invalid CPUID page address: {}", e),
+            ReadError::Io(e) => write!(f, "reading the CPUID page failed: {}", e),
+            ReadError::Parse(e) => write!(f, "invalid CPUID page: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Unaligned(e) => Some(e),
+            ReadError::Io(e) => Some(e),
+            ReadError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl ReadError {
+    /// A stable numeric code for the error, in the range 1100-1199.
+    pub fn code(&self) -> u32 {
+        match self {
+            ReadError::Unaligned(_) => 1100,
+            ReadError::Io(_) => 1101,
+            ReadError::Parse(_) => 1102,
+        }
+    }
+}
+
+/// The registers returned by `CPUID`.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Registers {
+    /// The value of `EAX`.
+    pub eax: u32,
+
+    /// The value of `EBX`.
+    pub ebx: u32,
+
+    /// The value of `ECX`.
+    pub ecx: u32,
+
+    /// The value of `EDX`.
+    pub edx: u32,
+}
+
+/// One function in the CPUID page.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CpuidFunction {
+    /// The leaf, passed in `EAX`.
+    pub eax_in: u32,
+
+    /// The subleaf, passed in `ECX`.
+    pub ecx_in: u32,
+
+    /// The value of `XCR0` the results apply to.
+    pub xcr0_in: u64,
+
+    /// The value of `XSS` the results apply to.
+    pub xss_in: u64,
+
+    /// The results.
+    pub out: Registers,
+}
+
+impl CpuidFunction {
+    /// Size of an encoded function.
+    pub const SIZE: usize = 0x30;
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let eax_in = reader.u32()?;
+        let ecx_in = reader.u32()?;
+        let xcr0_in = reader.u64()?;
+        let xss_in = reader.u64()?;
+        let out = Registers {
+            eax: reader.u32()?,
+            ebx: reader.u32()?,
+            ecx: reader.u32()?,
+            edx: reader.u32()?,
+        };
+        reader.reserved(8)?;
+
+        Ok(Self {
+            eax_in,
+            ecx_in,
+            xcr0_in,
+            xss_in,
+            out,
+        })
+    }
+}
+
+/// A function whose results in the CPUID page differ from `CPUID`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The function as recorded in the CPUID page.
+    pub page: CpuidFunction,
+
+    /// The results of `CPUID` for the same leaf and subleaf.
+    pub live: Registers,
+}
+
+/// The contents of the CPUID page.
+///
+/// See "CPUID Page" and "CPUID_FUNCTION Structure" in the SEV-SNP
+/// firmware ABI specification.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CpuidPage {
+    /// The functions in the page, in page order.
+    pub functions: Vec<CpuidFunction>,
+}
+
+impl CpuidPage {
+    /// Size of the CPUID page.
+    pub const SIZE: usize = 4096;
+
+    /// The maximum number of functions in the page.
+    pub const MAX_FUNCTIONS: usize = 64;
+
+    const HEADER: usize = 0x10;
+
+    /// Decodes the CPUID page.
+    ///
+    /// The input must be exactly one page long.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, false)
+    }
+
+    /// Decodes the CPUID page, rejecting non-zero reserved fields.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, true)
+    }
+
+    fn read(bytes: &[u8], strict: bool) -> Result<Self, Error> {
+        let mut reader = Reader::with_strict(bytes, strict);
+
+        let count = reader.u32()? as usize;
+        if count > Self::MAX_FUNCTIONS {
+            return Err(Error::new(0, ErrorKind::InvalidValue));
+        }
+        reader.reserved(Self::HEADER - reader.offset())?;
+
+        let functions = (0..count)
+            .map(|_| CpuidFunction::read(&mut reader))
+            .collect::<Result<_, _>>()?;
+
+        reader.skip((Self::MAX_FUNCTIONS - count) * CpuidFunction::SIZE)?;
+        reader.reserved(Self::SIZE - reader.offset())?;
+        reader.finish()?;
+
+        Ok(Self { functions })
+    }
+
+    /// Reads and decodes one page from `source`.
+    pub fn read_from(source: impl Read) -> Result<Self, ReadError> {
+        Self::load(source, false)
+    }
+
+    /// Reads and decodes one page from `source`, rejecting non-zero
+    /// reserved fields.
+    pub fn read_from_strict(source: impl Read) -> Result<Self, ReadError> {
+        Self::load(source, true)
+    }
+
+    /// Reads and decodes the page at `gpa` in `memory`.
+    ///
+    /// `memory` is a view of guest physical memory in which offsets are
+    /// guest physical addresses, e.g. `/dev/mem` opened in the guest.
+    pub fn read_at<R: Read + Seek>(memory: R, gpa: Gpa) -> Result<Self, ReadError> {
+        Self::load_at(memory, gpa, false)
+    }
+
+    /// Reads and decodes the page at `gpa` in `memory`, rejecting non-zero
+    /// reserved fields.
+    pub fn read_at_strict<R: Read + Seek>(memory: R, gpa: Gpa) -> Result<Self, ReadError> {
+        Self::load_at(memory, gpa, true)
+    }
+
+    fn load(mut source: impl Read, strict: bool) -> Result<Self, ReadError> {
+        let mut bytes = vec![0u8; Self::SIZE];
+        source.read_exact(&mut bytes).map_err(ReadError::Io)?;
+        Self::read(&bytes, strict).map_err(ReadError::Parse)
+    }
+
+    fn load_at<R: Read + Seek>(mut memory: R, gpa: Gpa, strict: bool) -> Result<Self, ReadError> {
+        if !gpa.is_aligned() {
+            return Err(ReadError::Unaligned(Unaligned(gpa)));
+        }
+
+        memory.seek(SeekFrom::Start(gpa.0)).map_err(ReadError::Io)?;
+        Self::load(memory, strict)
+    }
+
+    /// The results recorded for `leaf` and `subleaf`, if any.
+    ///
+    /// Leaves whose results depend on `XCR0` or `XSS` may appear more
+    /// than once; the first match is returned.
+    pub fn get(&self, leaf: u32, subleaf: u32) -> Option<&CpuidFunction> {
+        self.functions
+            .iter()
+            .find(|f| f.eax_in == leaf && f.ecx_in == subleaf)
+    }
+
+    /// Compares every function in the page with the results of `cpuid`.
+    ///
+    /// `cpuid` is called with the leaf and subleaf of each function. See
+    /// the [module documentation](self) for what a mismatch does and does
+    /// not show.
+    #[cfg_attr(
+        target_arch = "x86_64",
+        doc = "",
+        doc = "On x86-64, [`live`] executes the instruction."
+    )]
+    pub fn compare(&self, mut cpuid: impl FnMut(u32, u32) -> Registers) -> Vec<Mismatch> {
+        self.functions
+            .iter()
+            .filter_map(|page| {
+                let live = cpuid(page.eax_in, page.ecx_in);
+                if live == page.out {
+                    None
+                } else {
+                    Some(Mismatch { page: *page, live })
+                }
+            })
+            .collect()
+    }
+}
+
+crate::static_assert!(
+    CpuidPage::HEADER + CpuidPage::MAX_FUNCTIONS * CpuidFunction::SIZE <= CpuidPage::SIZE
+);
+
+/// Executes `CPUID` for `leaf` and `subleaf` on the current CPU.
+///
+/// In an SNP guest with a CPUID page installed, the kernel answers the
+/// instruction from that page, so the results are not independent of it.
+#[cfg(target_arch = "x86_64")]
+pub fn live(leaf: u32, subleaf: u32) -> Registers {
+    // SAFETY: `CPUID` is available on every x86-64 CPU.
+    #[allow(unused_unsafe)]
+    let r = unsafe { std::arch::x86_64::__cpuid_count(leaf, subleaf) };
+
+    Registers {
+        eax: r.eax,
+        ebx: r.ebx,
+        ecx: r.ecx,
+        edx: r.edx,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(leaf: u32, eax: u32) -> CpuidFunction {
+        CpuidFunction {
+            eax_in: leaf,
+            out: Registers {
+                eax,
+                ..Registers::default()
+            },
+            ..CpuidFunction::default()
+        }
+    }
+
+    fn example() -> Vec<u8> {
+        let mut bytes = vec![0u8; CpuidPage::SIZE];
+        bytes[0..4].copy_from_slice(&2u32.to_le_bytes());
+
+        let mut entry = &mut bytes[0x10..];
+        entry[0..4].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+        entry[0x18..0x1c].copy_from_slice(&0x8000_0021u32.to_le_bytes());
+
+        entry = &mut bytes[0x40..];
+        entry[0..4].copy_from_slice(&0xdu32.to_le_bytes());
+        entry[4..8].copy_from_slice(&1u32.to_le_bytes());
+        entry[0x08..0x10].copy_from_slice(&0xe7u64.to_le_bytes());
+        entry[0x24..0x28].copy_from_slice(&0x1234u32.to_le_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn decode() {
+        let page = CpuidPage::from_bytes_strict(&example()).unwrap();
+
+        assert_eq!(page.functions.len(), 2);
+        assert_eq!(page.functions[0], function(0x8000_0000, 0x8000_0021));
+        assert_eq!(
+            page.get(0xd, 1),
+            Some(&CpuidFunction {
+                eax_in: 0xd,
+                ecx_in: 1,
+                xcr0_in: 0xe7,
+                xss_in: 0,
+                out: Registers {
+                    edx: 0x1234,
+                    ..Registers::default()
+                },
+            })
+        );
+        assert_eq!(page.get(0xd, 0), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = example();
+        bytes[0] = 65;
+        let err = CpuidPage::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+
+        let mut bytes = example();
+        bytes[0x10 + 0x28] = 1;
+        assert!(CpuidPage::from_bytes(&bytes).is_ok());
+        let err = CpuidPage::from_bytes_strict(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x38);
+        assert_eq!(err.kind(), ErrorKind::NonZeroReserved);
+
+        let err = CpuidPage::from_bytes(&example()[..0x100]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Truncated { .. }));
+    }
+
+    #[test]
+    fn read_at() {
+        let mut memory = vec![0xffu8; 0x3000];
+        memory[0x1000..0x2000].copy_from_slice(&example());
+        let memory = io::Cursor::new(memory);
+
+        let page = CpuidPage::read_at(memory.clone(), Gpa(0x1000)).unwrap();
+        assert_eq!(page, CpuidPage::from_bytes(&example()).unwrap());
+
+        let err = CpuidPage::read_at(memory.clone(), Gpa(0x1001)).unwrap_err();
+        assert_eq!(err.code(), 1100);
+        let err = CpuidPage::read_at(memory.clone(), Gpa(0x3000)).unwrap_err();
+        assert!(matches!(err, ReadError::Io(ref e) if e.kind() == io::ErrorKind::UnexpectedEof));
+        let err = CpuidPage::read_at(memory.clone(), Gpa(0x2000)).unwrap_err();
+        assert!(matches!(err, ReadError::Parse(_)));
+
+        let mut memory = memory.into_inner();
+        memory[0x1000 + 0x10 + 0x28] = 1;
+        let memory = io::Cursor::new(memory);
+        assert!(CpuidPage::read_at(memory.clone(), Gpa(0x1000)).is_ok());
+        let err = CpuidPage::read_at_strict(memory.clone(), Gpa(0x1000)).unwrap_err();
+        assert!(matches!(err, ReadError::Parse(e) if e.kind() == ErrorKind::NonZeroReserved));
+        let err = CpuidPage::read_from_strict(&memory.get_ref()[0x1000..]).unwrap_err();
+        assert_eq!(err.code(), 1102);
+    }
+
+    #[test]
+    fn compare() {
+        let page = CpuidPage {
+            functions: vec![function(0, 0x10), function(1, 0x00a0_0f11)],
+        };
+
+        let mismatches = page.compare(|leaf, _| Registers {
+            eax: if leaf == 0 { 0x10 } else { 0x00a0_0f10 },
+            ..Registers::default()
+        });
+        assert_eq!(
+            mismatches,
+            [Mismatch {
+                page: function(1, 0x00a0_0f11),
+                live: Registers {
+                    eax: 0x00a0_0f10,
+                    ..Registers::default()
+                },
+            }]
+        );
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn live_cpuid() {
+        let page = CpuidPage {
+            functions: vec![CpuidFunction {
+                out: live(0, 0),
+                ..CpuidFunction::default()
+            }],
+        };
+
+        assert!(page.compare(live).is_empty());
+    }
+}
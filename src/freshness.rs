@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Timestamped challenges in `REPORT_DATA`, preventing report replay.
+//!
+//! Attestation reports carry no time of their own. Under this convention
+//! the relying party issues a [`Challenge`]: the current time and a MAC
+//! over it under a key only the relying party knows. The guest copies the
+//! challenge into `REPORT_DATA` together with 32 bytes of its own, e.g.
+//! the hash of a public key, and the verifier accepts the report only if
+//! the MAC is genuine and the timestamp lies within a [`Window`].
+//!
+//! The layout is [`SCHEMA`]. Times are seconds since the Unix epoch,
+//! supplied by the caller.
+
+use crate::report::AttestationReport;
+use crate::report_data::{self, Field, Schema};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::convert::TryInto;
+use std::fmt;
+
+/// The `REPORT_DATA` layout of a timestamped challenge.
+pub static SCHEMA: Schema = Schema {
+    magic: *b"SNPT",
+    version: 1,
+    fields: &[
+        Field {
+            name: "timestamp",
+            len: 8,
+        },
+        Field {
+            name: "tag",
+            len: 16,
+        },
+        Field {
+            name: "binding",
+            len: 32,
+        },
+    ],
+};
+
+/// Prefix of the MAC input, separating it from other uses of the key.
+const DOMAIN: &[u8] = b"snp-freshness-v1\0";
+
+/// Errors produced when checking the freshness of a report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// `REPORT_DATA` does not follow [`SCHEMA`].
+    Layout(report_data::Error),
+
+    /// The challenge was not issued with this key.
+    Tag,
+
+    /// The challenge is older than the window allows.
+    Expired {
+        /// The age of the challenge in seconds.
+        age: u64,
+    },
+
+    /// The challenge lies further in the future than the allowed skew.
+    Future,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Layout(e) => write!(f, "invalid challenge layout: {}", e),
+            Error::Tag => write!(f, "challenge tag mismatch"),
+            Error::Expired { age } => write!(f, "challenge is {} seconds old", age),
+            Error::Future => write!(f, "challenge issued in the future"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// A stable numeric code for the error, in the range 900-999.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::Layout(_) => 900,
+            Error::Tag => 901,
+            Error::Expired { .. } => 902,
+            Error::Future => 903,
+        }
+    }
+}
+
+fn mac(key: &[u8], timestamp: u64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(DOMAIN);
+    mac.update(&timestamp.to_le_bytes());
+    mac
+}
+
+/// A timestamp authenticated by the relying party.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    /// When the challenge was issued.
+    pub timestamp: u64,
+
+    /// A truncated HMAC-SHA256 of the timestamp.
+    pub tag: [u8; 16],
+}
+
+impl Challenge {
+    /// Issues a challenge for `timestamp` under the relying party's `key`.
+    pub fn issue(key: &[u8], timestamp: u64) -> Self {
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice(&mac(key, timestamp).finalize().into_bytes()[..16]);
+
+        Self { timestamp, tag }
+    }
+
+    /// The `REPORT_DATA` carrying this challenge and the guest's `binding`.
+    pub fn report_data(&self, binding: &[u8; 32]) -> [u8; report_data::SIZE] {
+        SCHEMA
+            .encode(&[
+                ("timestamp", &self.timestamp.to_le_bytes()),
+                ("tag", &self.tag),
+                ("binding", binding),
+            ])
+            .expect("values match the schema")
+    }
+}
+
+/// How far a challenge may lie from the verifier's current time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    /// The maximum age of a challenge in seconds.
+    pub max_age: u64,
+
+    /// How far in the future a challenge may lie, tolerating clock skew
+    /// between the issuing and the verifying host.
+    pub max_skew: u64,
+}
+
+/// A report that passed the freshness check.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fresh {
+    /// When the challenge was issued.
+    pub timestamp: u64,
+
+    /// The guest's binding value.
+    pub binding: [u8; 32],
+}
+
+impl Window {
+    /// Checks that `report` carries a genuine challenge within the window
+    /// around `now`.
+    pub fn check(&self, report: &AttestationReport, key: &[u8], now: u64) -> Result<Fresh, Error> {
+        let decoded = SCHEMA.decode(&report.report_data).map_err(Error::Layout)?;
+        let field = |name| decoded.get(name).expect("field of the schema");
+
+        let timestamp = u64::from_le_bytes(field("timestamp").try_into().unwrap());
+        mac(key, timestamp)
+            .verify_truncated_left(field("tag"))
+            .map_err(|_| Error::Tag)?;
+
+        if timestamp > now.saturating_add(self.max_skew) {
+            return Err(Error::Future);
+        }
+
+        let age = now.saturating_sub(timestamp);
+        if age > self.max_age {
+            return Err(Error::Expired { age });
+        }
+
+        Ok(Fresh {
+            timestamp,
+            binding: field("binding").try_into().unwrap(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"relying party key";
+
+    const WINDOW: Window = Window {
+        max_age: 300,
+        max_skew: 5,
+    };
+
+    fn with_data(data: [u8; 64]) -> AttestationReport {
+        let mut report = AttestationReport::from_bytes(&crate::report::tests::example()).unwrap();
+        report.report_data = data;
+        report
+    }
+
+    #[test]
+    fn fresh() {
+        let report = with_data(Challenge::issue(KEY, 1000).report_data(&[0x42; 32]));
+
+        assert_eq!(
+            WINDOW.check(&report, KEY, 1100),
+            Ok(Fresh {
+                timestamp: 1000,
+                binding: [0x42; 32]
+            })
+        );
+        assert!(WINDOW.check(&report, KEY, 997).is_ok());
+    }
+
+    #[test]
+    fn stale() {
+        let report = with_data(Challenge::issue(KEY, 1000).report_data(&[0; 32]));
+
+        assert_eq!(
+            WINDOW.check(&report, KEY, 1301),
+            Err(Error::Expired { age: 301 })
+        );
+        assert_eq!(
+            Error::Expired { age: 301 }.to_string(),
+            "challenge is 301 seconds old"
+        );
+        assert_eq!(WINDOW.check(&report, KEY, 994), Err(Error::Future));
+    }
+
+    #[test]
+    fn forged() {
+        let report = with_data(Challenge::issue(b"other key", 1000).report_data(&[0; 32]));
+        assert_eq!(WINDOW.check(&report, KEY, 1000), Err(Error::Tag));
+
+        let mut challenge = Challenge::issue(KEY, 1000);
+        challenge.timestamp = 2000;
+        let report = with_data(challenge.report_data(&[0; 32]));
+        assert_eq!(WINDOW.check(&report, KEY, 2000), Err(Error::Tag));
+
+        let report = with_data([0; 64]);
+        assert_eq!(
+            WINDOW.check(&report, KEY, 1000),
+            Err(Error::Layout(report_data::Error::BadMagic))
+        );
+    }
+}
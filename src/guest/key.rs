@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keys derived by the firmware with `MSG_KEY_REQ`.
+//!
+//! A guest usually needs more than one secret, e.g. one for disk
+//! encryption and one for API tokens. Rather than requesting a firmware
+//! key per purpose or having each consumer invent its own expansion,
+//! [`DerivedKey::expand`] runs HKDF-SHA256 over the firmware key with an
+//! application-supplied label. Different labels yield independent keys.
+//!
+//! Each `MSG_KEY_REQ` is a round trip to the firmware. A [`KeyCache`]
+//! remembers the keys returned per request, so a guest that needs the
+//! same key repeatedly only asks the firmware once.
+
+use super::message::KeyRequest;
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Prefix of every HKDF info string, separating these keys from other
+/// uses of the firmware key.
+const DOMAIN: &[u8] = b"snp-derived-key-v1\0";
+
+/// A 256-bit key returned in `MSG_KEY_RSP`.
+///
+/// The key is overwritten with zeroes when dropped. It is neither `Copy`
+/// nor `Clone`, so that no copies are left behind unnoticed; a copy has to
+/// be made explicitly from [`DerivedKey::as_bytes`]. Comparisons take the
+/// same time wherever the keys differ.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct DerivedKey([u8; 32]);
+
+impl PartialEq for DerivedKey {
+    fn eq(&self, other: &Self) -> bool {
+        let diff = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+
+        // SAFETY: `diff` is a valid, aligned local.
+        unsafe { std::ptr::read_volatile(&diff) == 0 }
+    }
+}
+
+impl Eq for DerivedKey {}
+
+impl Drop for DerivedKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for DerivedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("DerivedKey(<redacted>)")
+    }
+}
+
+impl From<[u8; 32]> for DerivedKey {
+    fn from(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+}
+
+impl DerivedKey {
+    /// The raw firmware key.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Derives an `N` byte key for the purpose named by `label`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` exceeds the HKDF-SHA256 limit of 8160 bytes.
+    pub fn expand<const N: usize>(&self, label: &str) -> [u8; N] {
+        let mut info = DOMAIN.to_vec();
+        info.extend_from_slice(label.as_bytes());
+
+        let mut okm = [0u8; N];
+        Hkdf::<Sha256>::new(None, &self.0)
+            .expand(&info, &mut okm)
+            .expect("output length within HKDF-SHA256 limit");
+        okm
+    }
+
+    /// Overwrites the key with zeroes in a way the compiler keeps.
+    fn zeroize(&mut self) {
+        for byte in self.0.iter_mut() {
+            // SAFETY: `byte` is a valid, aligned and exclusive reference.
+            unsafe { std::ptr::write_volatile(byte, 0) };
+        }
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Derived keys remembered per [`KeyRequest`].
+///
+/// Each key is boxed when it enters the cache, so it stays in place while
+/// the map grows, and callers only get to borrow it. Keys are overwritten
+/// with zeroes when evicted, cleared or dropped with the cache.
+#[derive(Default)]
+pub struct KeyCache {
+    keys: HashMap<KeyRequest, Box<DerivedKey>>,
+}
+
+impl fmt::Debug for KeyCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyCache")
+            .field("len", &self.keys.len())
+            .finish()
+    }
+}
+
+impl KeyCache {
+    /// An empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The key for `request`, calling `derive` to obtain it from the
+    /// firmware unless it is cached. Failures are not cached.
+    pub fn get_or_derive<E>(
+        &mut self,
+        request: &KeyRequest,
+        derive: impl FnOnce(&KeyRequest) -> Result<DerivedKey, E>,
+    ) -> Result<&DerivedKey, E> {
+        if !self.keys.contains_key(request) {
+            let key = Box::new(derive(request)?);
+            self.keys.insert(*request, key);
+        }
+
+        Ok(&self.keys[request])
+    }
+
+    /// Whether the key for `request` is cached.
+    pub fn contains(&self, request: &KeyRequest) -> bool {
+        self.keys.contains_key(request)
+    }
+
+    /// Removes the key for `request`, returning whether it was cached.
+    pub fn evict(&mut self, request: &KeyRequest) -> bool {
+        self.keys.remove(request).is_some()
+    }
+
+    /// Removes all keys.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// The number of cached keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand() {
+        let key = DerivedKey::from([0x42; 32]);
+
+        let disk: [u8; 32] = key.expand("disk-encryption");
+        let token: [u8; 32] = key.expand("api-token");
+        assert_ne!(disk, token);
+        assert_eq!(key.expand::<32>("disk-encryption"), disk);
+        assert_eq!(key.expand::<16>("disk-encryption"), disk[..16]);
+
+        let other = DerivedKey::from([0x43; 32]);
+        assert_ne!(other.expand::<32>("disk-encryption"), disk);
+    }
+
+    #[test]
+    fn debug_hides_key() {
+        let key = DerivedKey::from([0x42; 32]);
+
+        assert_eq!(format!("{:?}", key), "DerivedKey(<redacted>)");
+    }
+
+    #[test]
+    fn cache() {
+        use crate::guest::message::RootKey;
+        use crate::report::TcbVersion;
+
+        let request = KeyRequest {
+            root_key: RootKey::Vcek,
+            guest_field_select: 0,
+            vmpl: 0,
+            guest_svn: 0,
+            tcb_version: TcbVersion::default(),
+        };
+        let other = KeyRequest { vmpl: 1, ..request };
+
+        let mut cache = KeyCache::new();
+        let mut calls = 0;
+        let mut derive = |r: &KeyRequest| -> Result<DerivedKey, ()> {
+            calls += 1;
+            Ok(DerivedKey::from([r.vmpl as u8; 32]))
+        };
+
+        let first = cache.get_or_derive(&request, &mut derive).unwrap() as *const DerivedKey;
+        assert_eq!(
+            cache.get_or_derive(&request, &mut derive),
+            Ok(&[0; 32].into())
+        );
+        assert_eq!(
+            cache.get_or_derive(&other, &mut derive),
+            Ok(&[1; 32].into())
+        );
+
+        // Growing the map does not move the keys.
+        for vmpl in 2..64 {
+            cache
+                .get_or_derive(&KeyRequest { vmpl, ..request }, &mut derive)
+                .unwrap();
+        }
+        assert_eq!(
+            cache.get_or_derive(&request, &mut derive).unwrap() as *const DerivedKey,
+            first
+        );
+        assert_eq!(calls, 64);
+
+        for vmpl in 2..64 {
+            assert!(cache.evict(&KeyRequest { vmpl, ..request }));
+        }
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(
+            cache.get_or_derive(&KeyRequest { vmpl: 2, ..request }, |_| Err(())),
+            Err(())
+        );
+        assert_eq!(cache.len(), 2);
+
+        assert!(cache.evict(&request));
+        assert!(!cache.evict(&request));
+        assert!(cache.contains(&other));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn eq() {
+        let key = DerivedKey::from([0x42; 32]);
+        assert_eq!(key, DerivedKey::from([0x42; 32]));
+
+        for i in [0, 17, 31].iter() {
+            let mut other = [0x42; 32];
+            other[*i] ^= 0x80;
+            assert_ne!(key, DerivedKey::from(other));
+        }
+    }
+
+    #[test]
+    fn zeroize() {
+        let mut key = DerivedKey::from([0x42; 32]);
+        key.zeroize();
+        assert_eq!(key.as_bytes(), &[0; 32]);
+    }
+}
@@ -0,0 +1,436 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Messages exchanged between the guest and the firmware.
+//!
+//! Guest messages travel through memory shared with the hypervisor, so
+//! everything in here is decoded as untrusted input.
+
+use super::key::DerivedKey;
+use crate::parse::{Error, ErrorKind, Reader};
+use crate::report::{AttestationReport, TcbVersion};
+
+/// The header preceding every guest message.
+///
+/// See "Message Header Format" in the SEV-SNP firmware ABI specification.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Authentication tag of the message.
+    pub authtag: [u8; 32],
+
+    /// Sequence number of the message.
+    pub msg_seqno: u64,
+
+    /// The AEAD algorithm protecting the payload.
+    pub algo: u8,
+
+    /// Version of the header format.
+    pub hdr_version: u8,
+
+    /// Size of the header, and thus the offset of the payload.
+    pub hdr_size: u16,
+
+    /// Type of the payload.
+    pub msg_type: u8,
+
+    /// Version of the payload format.
+    pub msg_version: u8,
+
+    /// Size of the payload.
+    pub msg_size: u16,
+
+    /// Index of the VMPCK protecting the message.
+    pub msg_vmpck: u8,
+}
+
+impl Header {
+    /// Size of a version 1 header.
+    pub const SIZE: usize = 0x60;
+
+    /// The only header version defined by the specification.
+    pub const VERSION: u8 = 1;
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let authtag = reader.array()?;
+        let msg_seqno = reader.u64()?;
+        reader.reserved(8)?;
+        let algo = reader.u8()?;
+
+        let hdr_version = reader.u8()?;
+        if hdr_version != Self::VERSION {
+            return Err(Error::new(
+                reader.offset() - 1,
+                ErrorKind::UnsupportedVersion(hdr_version.into()),
+            ));
+        }
+
+        let hdr_size = reader.u16()?;
+        if (hdr_size as usize) < Self::SIZE {
+            return Err(Error::new(reader.offset() - 2, ErrorKind::InvalidValue));
+        }
+
+        let msg_type = reader.u8()?;
+        let msg_version = reader.u8()?;
+        let msg_size = reader.u16()?;
+        reader.reserved(4)?;
+
+        let msg_vmpck = reader.u8()?;
+        if msg_vmpck > 3 {
+            return Err(Error::new(reader.offset() - 1, ErrorKind::InvalidValue));
+        }
+        reader.reserved(Self::SIZE - reader.offset())?;
+
+        Ok(Self {
+            authtag,
+            msg_seqno,
+            algo,
+            hdr_version,
+            hdr_size,
+            msg_type,
+            msg_version,
+            msg_size,
+            msg_vmpck,
+        })
+    }
+}
+
+/// A guest message: a header and the (still encrypted) payload.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Message<'a> {
+    /// The message header.
+    pub header: Header,
+
+    /// The payload, as described by `hdr_size` and `msg_size`.
+    pub payload: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    /// Decodes a guest message.
+    ///
+    /// The buffer may extend past the payload, as messages are exchanged
+    /// in page-sized buffers.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        Self::read(bytes, false)
+    }
+
+    /// Decodes a guest message, rejecting non-zero reserved header fields.
+    pub fn from_bytes_strict(bytes: &'a [u8]) -> Result<Self, Error> {
+        Self::read(bytes, true)
+    }
+
+    fn read(bytes: &'a [u8], strict: bool) -> Result<Self, Error> {
+        let mut reader = Reader::with_strict(bytes, strict);
+        let header = Header::read(&mut reader)?;
+
+        reader.skip(header.hdr_size as usize - Header::SIZE)?;
+        let payload = reader.bytes(header.msg_size.into())?;
+
+        Ok(Self { header, payload })
+    }
+}
+
+/// The decrypted payload of a `MSG_REPORT_RSP` message.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReportResponse {
+    /// The firmware status of the request.
+    pub status: u32,
+
+    /// The report, if the request succeeded.
+    pub report: Option<AttestationReport>,
+}
+
+impl ReportResponse {
+    /// Decodes a `MSG_REPORT_RSP` payload.
+    ///
+    /// The report is only decoded when `status` indicates success.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, false)
+    }
+
+    /// Decodes a `MSG_REPORT_RSP` payload and its report, rejecting
+    /// non-zero reserved fields in both.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, true)
+    }
+
+    fn read(bytes: &[u8], strict: bool) -> Result<Self, Error> {
+        let mut reader = Reader::with_strict(bytes, strict);
+
+        let status = reader.u32()?;
+        let size = reader.u32()? as usize;
+        reader.reserved(24)?;
+
+        if status != 0 {
+            return Ok(Self {
+                status,
+                report: None,
+            });
+        }
+
+        let start = reader.offset();
+        if size != AttestationReport::SIZE {
+            return Err(Error::new(4, ErrorKind::InvalidValue));
+        }
+
+        let bytes = reader.bytes(size)?;
+        let report = if reader.strict() {
+            AttestationReport::from_bytes_strict(bytes)
+        } else {
+            AttestationReport::from_bytes(bytes)
+        }
+        .map_err(|e| Error::new(start + e.offset(), e.kind()))?;
+
+        Ok(Self {
+            status,
+            report: Some(report),
+        })
+    }
+}
+
+/// The root key a derived key is based on.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RootKey {
+    /// The Versioned Chip Endorsement Key.
+    Vcek,
+
+    /// The VM Root Key, supplied by the migration agent.
+    Vmrk,
+}
+
+/// The payload of a `MSG_KEY_REQ` message.
+///
+/// See "MSG_KEY_REQ Message Structure" in the SEV-SNP firmware ABI
+/// specification. Requests with equal fields yield the same key.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyRequest {
+    /// The root key to derive from.
+    pub root_key: RootKey,
+
+    /// Which guest fields are mixed into the key.
+    pub guest_field_select: u64,
+
+    /// The VMPL to mix into the key; at or above the requesting VMPL.
+    pub vmpl: u32,
+
+    /// The guest SVN to mix into the key, if selected.
+    pub guest_svn: u32,
+
+    /// The TCB version to mix into the key, if selected.
+    pub tcb_version: TcbVersion,
+}
+
+impl KeyRequest {
+    /// Size of a `MSG_KEY_REQ` payload.
+    pub const SIZE: usize = 0x20;
+
+    /// Encodes the request.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let select: u32 = match self.root_key {
+            RootKey::Vcek => 0,
+            RootKey::Vmrk => 1,
+        };
+
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0x00..0x04].copy_from_slice(&select.to_le_bytes());
+        bytes[0x08..0x10].copy_from_slice(&self.guest_field_select.to_le_bytes());
+        bytes[0x10..0x14].copy_from_slice(&self.vmpl.to_le_bytes());
+        bytes[0x14..0x18].copy_from_slice(&self.guest_svn.to_le_bytes());
+        bytes[0x18..0x20].copy_from_slice(&u64::from(self.tcb_version).to_le_bytes());
+        bytes
+    }
+}
+
+/// The decrypted payload of a `MSG_KEY_RSP` message.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct KeyResponse {
+    /// The firmware status of the request.
+    pub status: u32,
+
+    /// The derived key, if the request succeeded.
+    pub key: Option<DerivedKey>,
+}
+
+impl KeyResponse {
+    /// Size of a `MSG_KEY_RSP` payload.
+    pub const SIZE: usize = 0x40;
+
+    /// Decodes a `MSG_KEY_RSP` payload.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, false)
+    }
+
+    /// Decodes a `MSG_KEY_RSP` payload, rejecting non-zero reserved fields.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, true)
+    }
+
+    fn read(bytes: &[u8], strict: bool) -> Result<Self, Error> {
+        let mut reader = Reader::with_strict(bytes, strict);
+
+        let status = reader.u32()?;
+        reader.reserved(0x1c)?;
+        let key = reader.array::<32>()?;
+        reader.finish()?;
+
+        Ok(Self {
+            status,
+            key: if status == 0 { Some(key.into()) } else { None },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(hdr_size: u16, msg_size: u16) -> Vec<u8> {
+        let mut bytes = vec![0u8; Header::SIZE];
+
+        bytes[0x00..0x20].copy_from_slice(&[0xee; 32]);
+        bytes[0x20..0x28].copy_from_slice(&5u64.to_le_bytes());
+        bytes[0x30] = 1;
+        bytes[0x31] = 1;
+        bytes[0x32..0x34].copy_from_slice(&hdr_size.to_le_bytes());
+        bytes[0x34] = 6;
+        bytes[0x35] = 1;
+        bytes[0x36..0x38].copy_from_slice(&msg_size.to_le_bytes());
+        bytes[0x3c] = 2;
+
+        bytes
+    }
+
+    #[test]
+    fn decode() {
+        let mut bytes = header(0x60, 3);
+        bytes.extend_from_slice(&[1, 2, 3, 0, 0]);
+
+        let msg = Message::from_bytes(&bytes).unwrap();
+        assert_eq!(msg.header.authtag, [0xee; 32]);
+        assert_eq!(msg.header.msg_seqno, 5);
+        assert_eq!(msg.header.algo, 1);
+        assert_eq!(msg.header.msg_type, 6);
+        assert_eq!(msg.header.msg_vmpck, 2);
+        assert_eq!(msg.payload, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn larger_header() {
+        let mut bytes = header(0x68, 1);
+        bytes.extend_from_slice(&[0; 8]);
+        bytes.push(9);
+
+        let msg = Message::from_bytes(&bytes).unwrap();
+        assert_eq!(msg.payload, &[9]);
+    }
+
+    #[test]
+    fn malformed() {
+        let err = Message::from_bytes(&header(0x10, 0)).unwrap_err();
+        assert_eq!(err.offset(), 0x32);
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+
+        let err = Message::from_bytes(&header(0x60, 4)).unwrap_err();
+        assert_eq!(err.offset(), 0x60);
+        assert_eq!(err.kind(), ErrorKind::Truncated { needed: 4 });
+
+        let mut bytes = header(0x60, 0);
+        bytes[0x31] = 2;
+        let err = Message::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x31);
+        assert_eq!(err.kind(), ErrorKind::UnsupportedVersion(2));
+
+        let mut bytes = header(0x60, 0);
+        bytes[0x3c] = 4;
+        let err = Message::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x3c);
+    }
+
+    #[test]
+    fn report_response() {
+        let mut bytes = vec![0u8; 0x20];
+        bytes[4..8].copy_from_slice(&(AttestationReport::SIZE as u32).to_le_bytes());
+        bytes.extend_from_slice(&crate::report::tests::example());
+
+        let rsp = ReportResponse::from_bytes(&bytes).unwrap();
+        assert_eq!(rsp.status, 0);
+        assert_eq!(rsp.report.unwrap().guest_svn, 7);
+
+        bytes[0x20] = 0x42;
+        let err = ReportResponse::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x20);
+        assert_eq!(err.kind(), ErrorKind::UnsupportedVersion(0x42));
+
+        bytes[0] = 0x16;
+        let rsp = ReportResponse::from_bytes(&bytes).unwrap();
+        assert_eq!(rsp.status, 0x16);
+        assert_eq!(rsp.report, None);
+    }
+
+    #[test]
+    fn strict() {
+        let mut bytes = header(0x60, 0);
+        assert!(Message::from_bytes_strict(&bytes).is_ok());
+
+        bytes[0x3f] = 1;
+        assert!(Message::from_bytes(&bytes).is_ok());
+        let err = Message::from_bytes_strict(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x3f);
+        assert_eq!(err.kind(), ErrorKind::NonZeroReserved);
+
+        let mut bytes = vec![0u8; 0x20];
+        bytes[4..8].copy_from_slice(&(AttestationReport::SIZE as u32).to_le_bytes());
+        bytes.extend_from_slice(&crate::report::tests::example());
+        assert!(ReportResponse::from_bytes_strict(&bytes).is_ok());
+
+        bytes[0x20 + 0x4c] = 1;
+        assert!(ReportResponse::from_bytes(&bytes).is_ok());
+        let err = ReportResponse::from_bytes_strict(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x20 + 0x4c);
+    }
+
+    #[test]
+    fn key_response() {
+        let mut bytes = vec![0u8; KeyResponse::SIZE];
+        bytes[0x20..].copy_from_slice(&[0x42; 32]);
+
+        let rsp = KeyResponse::from_bytes(&bytes).unwrap();
+        assert_eq!(rsp.key, Some(DerivedKey::from([0x42; 32])));
+
+        bytes[0x10] = 1;
+        assert!(KeyResponse::from_bytes(&bytes).is_ok());
+        let err = KeyResponse::from_bytes_strict(&bytes).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonZeroReserved);
+
+        bytes[0] = 0x16;
+        assert_eq!(KeyResponse::from_bytes(&bytes).unwrap().key, None);
+
+        let err = KeyResponse::from_bytes(&bytes[..0x30]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated { needed: 32 });
+    }
+
+    #[test]
+    fn key_request() {
+        let request = KeyRequest {
+            root_key: RootKey::Vmrk,
+            guest_field_select: 0b10_0001,
+            vmpl: 1,
+            guest_svn: 7,
+            tcb_version: TcbVersion {
+                bootloader: 3,
+                tee: 0,
+                snp: 8,
+                microcode: 115,
+            },
+        };
+
+        let bytes = request.to_bytes();
+        assert_eq!(bytes[0x00..0x08], [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes[0x08..0x10], 0b10_0001u64.to_le_bytes());
+        assert_eq!(bytes[0x10..0x18], [1, 0, 0, 0, 7, 0, 0, 0]);
+        assert_eq!(bytes[0x18..0x20], [3, 0, 0, 0, 0, 0, 8, 115]);
+    }
+}
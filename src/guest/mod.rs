@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Components used from within an SNP guest.
+
+pub mod key;
+pub mod message;
+pub mod tsm;
+
+use crate::certs::CertTable;
+use crate::report::AttestationReport;
+
+use std::fmt;
+
+/// An attestation report and the certificates that came with it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Evidence {
+    /// The attestation report.
+    pub report: AttestationReport,
+
+    /// The certificate table the host supplied, if any.
+    pub certs: Option<CertTable>,
+}
+
+/// A kernel interface through which the guest obtains evidence.
+///
+/// Application code written against this trait does not depend on which
+/// report interface the guest kernel offers.
+pub trait EvidenceProvider {
+    /// The error produced when a request fails.
+    type Error: std::error::Error;
+
+    /// Whether the interface is usable in this guest.
+    fn available(&self) -> bool;
+
+    /// Requests a report carrying `report_data`, at `vmpl` or at the
+    /// interface's default level if `None`.
+    fn evidence(&self, report_data: &[u8; 64], vmpl: Option<u32>) -> Result<Evidence, Self::Error>;
+}
+
+/// The report interfaces this crate can use.
+///
+/// New interfaces are added as variants, so code that selects one with
+/// [`provider`] keeps working as the list grows.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Provider {
+    /// The configfs-tsm report interface.
+    Tsm(tsm::Tsm),
+}
+
+/// Errors produced by a [`Provider`].
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The configfs-tsm request failed.
+    Tsm(tsm::Error),
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProviderError::Tsm(e) => write!(f, "configfs-tsm: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProviderError::Tsm(e) => Some(e),
+        }
+    }
+}
+
+impl ProviderError {
+    /// The stable numeric code of the underlying error.
+    pub fn code(&self) -> u32 {
+        match self {
+            ProviderError::Tsm(e) => e.code(),
+        }
+    }
+}
+
+impl EvidenceProvider for Provider {
+    type Error = ProviderError;
+
+    fn available(&self) -> bool {
+        match self {
+            Provider::Tsm(tsm) => tsm.available(),
+        }
+    }
+
+    fn evidence(&self, report_data: &[u8; 64], vmpl: Option<u32>) -> Result<Evidence, Self::Error> {
+        match self {
+            Provider::Tsm(tsm) => tsm.report(report_data, vmpl).map_err(ProviderError::Tsm),
+        }
+    }
+}
+
+/// Selects the first available evidence provider of this guest.
+///
+/// Only configfs-tsm is supported at the moment.
+pub fn provider() -> Option<Provider> {
+    let tsm = tsm::Tsm::new();
+    if tsm.available() {
+        Some(Provider::Tsm(tsm))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider() {
+        let provider = Provider::Tsm(tsm::Tsm::with_root(
+            std::env::temp_dir().join("snp-provider-missing"),
+        ));
+        assert!(!provider.available());
+
+        let err = provider.evidence(&[0; 64], None).unwrap_err();
+        assert!(matches!(err, ProviderError::Tsm(tsm::Error::Io(_))));
+        assert_eq!(
+            err.code(),
+            tsm::Error::Io(std::io::ErrorKind::NotFound.into()).code()
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Attestation reports through the kernel's configfs-tsm interface.
+//!
+//! Since Linux 6.7, guests can request reports without the sev-guest ioctl
+//! by creating a directory under `/sys/kernel/config/tsm/report`, writing
+//! the report data to its `inblob` and reading the report back from
+//! `outblob`. This module drives that interface and decodes the result.
+//!
+//! The interface is shared by every confidential computing technology, so
+//! [`Tsm::report`] checks that the `provider` is `sev_guest` before
+//! decoding anything.
+
+use super::{Evidence, EvidenceProvider};
+use crate::certs::CertTable;
+use crate::parse;
+use crate::report::AttestationReport;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The provider name of the SEV-SNP guest driver.
+pub const PROVIDER: &str = "sev_guest";
+
+/// Errors produced while requesting a report through configfs-tsm.
+#[derive(Debug)]
+pub enum Error {
+    /// Accessing configfs failed.
+    Io(io::Error),
+
+    /// The report was produced by another technology.
+    Provider(String),
+
+    /// The request was modified concurrently by another writer.
+    Conflict,
+
+    /// The report or certificate table could not be decoded.
+    Parse(parse::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "configfs-tsm access failed: {}", e),
+            Error::Provider(p) => write!(f, "unexpected TSM provider {:?}", p),
+            Error::Conflict => write!(f, "report request was modified concurrently"),
+            Error::Parse(e) => write!(f, "invalid report: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// A stable numeric code for the error, in the range 600-699.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::Io(_) => 600,
+            Error::Provider(_) => 601,
+            Error::Conflict => 602,
+            Error::Parse(_) => 603,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<parse::Error> for Error {
+    fn from(e: parse::Error) -> Self {
+        Error::Parse(e)
+    }
+}
+
+/// A handle to the configfs-tsm report interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tsm {
+    root: PathBuf,
+}
+
+impl Default for Tsm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tsm {
+    /// The default location of the report interface.
+    pub const ROOT: &'static str = "/sys/kernel/config/tsm/report";
+
+    /// Uses the interface at its default location.
+    pub fn new() -> Self {
+        Self::with_root(Self::ROOT)
+    }
+
+    /// Uses the interface mounted at `root`.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Whether the kernel offers the interface.
+    pub fn available(&self) -> bool {
+        self.root.is_dir()
+    }
+
+    /// Requests a report carrying `report_data`.
+    ///
+    /// The report is requested at `vmpl`, or at the kernel's default if
+    /// `None`.
+    pub fn report(&self, report_data: &[u8; 64], vmpl: Option<u32>) -> Result<Evidence, Error> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let entry = self.root.join(format!(
+            "snp-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::create_dir(&entry)?;
+        let result = request(&entry, report_data, vmpl);
+        let removed = fs::remove_dir(&entry);
+
+        let report = result?;
+        removed?;
+        Ok(report)
+    }
+}
+
+impl EvidenceProvider for Tsm {
+    type Error = Error;
+
+    fn available(&self) -> bool {
+        Tsm::available(self)
+    }
+
+    fn evidence(&self, report_data: &[u8; 64], vmpl: Option<u32>) -> Result<Evidence, Error> {
+        self.report(report_data, vmpl)
+    }
+}
+
+fn generation(entry: &Path) -> Result<u64, Error> {
+    fs::read_to_string(entry.join("generation"))?
+        .trim()
+        .parse()
+        .map_err(|_| Error::Io(io::Error::new(io::ErrorKind::InvalidData, "bad generation")))
+}
+
+/// Performs the request on a freshly created `entry`.
+///
+/// Every write to the entry advances its generation, so after our own
+/// writes it must equal their number; anything else means another
+/// process wrote to the entry and the report may not be ours.
+fn request(entry: &Path, report_data: &[u8; 64], vmpl: Option<u32>) -> Result<Evidence, Error> {
+    let mut expected = 1;
+
+    if let Some(vmpl) = vmpl {
+        fs::write(entry.join("privlevel"), vmpl.to_string())?;
+        expected += 1;
+    }
+    fs::write(entry.join("inblob"), &report_data[..])?;
+
+    let provider = fs::read_to_string(entry.join("provider"))?;
+    if provider.trim() != PROVIDER {
+        return Err(Error::Provider(provider.trim().into()));
+    }
+
+    let outblob = fs::read(entry.join("outblob"))?;
+    let auxblob = match fs::read(entry.join("auxblob")) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    if generation(entry)? != expected {
+        return Err(Error::Conflict);
+    }
+
+    let report = AttestationReport::from_bytes(&outblob)?;
+    let certs = if auxblob.is_empty() {
+        None
+    } else {
+        Some(CertTable::from_bytes(&auxblob)?)
+    };
+
+    Ok(Evidence { report, certs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out an entry the way configfs would after the request.
+    fn entry(name: &str, provider: &str, generation: u64) -> PathBuf {
+        let entry = std::env::temp_dir().join(format!("snp-tsm-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&entry);
+        fs::create_dir_all(&entry).unwrap();
+
+        fs::write(entry.join("generation"), format!("{}\n", generation)).unwrap();
+        fs::write(entry.join("provider"), format!("{}\n", provider)).unwrap();
+        fs::write(entry.join("outblob"), crate::report::tests::example()).unwrap();
+        entry
+    }
+
+    #[test]
+    fn report() {
+        let entry = entry("report", PROVIDER, 0);
+
+        // configfs would have advanced the generation with the write.
+        assert!(matches!(
+            request(&entry, &[0x33; 64], None),
+            Err(Error::Conflict)
+        ));
+
+        fs::write(entry.join("generation"), "1\n").unwrap();
+        let report = request(&entry, &[0x33; 64], None).unwrap();
+        assert_eq!(report.report.report_data, [0x33; 64]);
+        assert_eq!(report.certs, None);
+        assert_eq!(fs::read(entry.join("inblob")).unwrap(), [0x33; 64]);
+
+        fs::write(entry.join("generation"), "2\n").unwrap();
+        fs::write(entry.join("auxblob"), [0; 24]).unwrap();
+        let report = request(&entry, &[0x33; 64], Some(1)).unwrap();
+        assert_eq!(report.certs.unwrap().entries, vec![]);
+        assert_eq!(fs::read_to_string(entry.join("privlevel")).unwrap(), "1");
+
+        fs::remove_dir_all(&entry).unwrap();
+    }
+
+    #[test]
+    fn provider() {
+        let entry = entry("provider", "tdx_guest", 1);
+
+        match request(&entry, &[0; 64], None) {
+            Err(Error::Provider(p)) => assert_eq!(p, "tdx_guest"),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        fs::remove_dir_all(&entry).unwrap();
+    }
+
+    #[test]
+    fn unavailable() {
+        let tsm = Tsm::with_root(std::env::temp_dir().join("snp-tsm-missing"));
+
+        assert!(!EvidenceProvider::available(&tsm));
+        assert!(matches!(tsm.evidence(&[0; 64], None), Err(Error::Io(_))));
+    }
+}
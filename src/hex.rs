@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hex encoding of byte fields.
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(any(test, feature = "serde"))]
+pub(crate) fn decode<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    let hex = hex.as_bytes();
+    if hex.len() != 2 * N {
+        return None;
+    }
+
+    let mut bytes = [0u8; N];
+    for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+        let hi = (pair[0] as char).to_digit(16)?;
+        let lo = (pair[1] as char).to_digit(16)?;
+        *byte = (hi << 4 | lo) as u8;
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        assert_eq!(encode(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(decode::<3>("00ab7f"), Some([0x00, 0xab, 0x7f]));
+        assert_eq!(decode::<3>("00AB7F"), Some([0x00, 0xab, 0x7f]));
+        assert_eq!(decode::<3>("00ab7"), None);
+        assert_eq!(decode::<2>("zz00"), None);
+        assert_eq!(decode::<1>("+f"), None);
+    }
+}
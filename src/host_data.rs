@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Binding the VMM's configuration to the guest through `HOST_DATA`.
+//!
+//! `HOST_DATA` is supplied by the host at `SNP_LAUNCH_FINISH` and copied
+//! into every attestation report. A [`VmmConfig`] collects the parts of
+//! the host-side configuration a tenant cares about, such as the device
+//! model or the kernel command line, and hashes them into a value for
+//! that field. A verifier that knows the expected configuration rebuilds
+//! it and checks the report with [`VmmConfig::matches`], or requires the
+//! digest through [`AppraisalPolicy::host_data`](crate::appraisal::AppraisalPolicy::host_data).
+//!
+//! To tie reports to a specific VM, e.g. in a multi-tenant inventory,
+//! include the cloud instance ID with [`VmmConfig::with_instance_id`]. A
+//! configuration holding only the instance ID is a valid binding too. If
+//! the guest owner signs an ID block per instance instead, the instance
+//! ID fits into `IMAGE_ID` and is checked with
+//! [`AppraisalPolicy::image_id`](crate::appraisal::AppraisalPolicy::image_id).
+
+use crate::certs::Guid;
+use crate::report::AttestationReport;
+
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// The name under which the instance ID is stored.
+pub const INSTANCE_ID: &str = "instance_id";
+
+/// Prefix of the hashed encoding, separating it from other uses of SHA-256.
+const DOMAIN: &[u8] = b"snp-vmm-config-v1\0";
+
+/// A set of named VMM configuration values.
+///
+/// Values are hashed in the order of their names, so the digest does not
+/// depend on the order they were added in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VmmConfig {
+    values: BTreeMap<String, Vec<u8>>,
+}
+
+impl VmmConfig {
+    /// An empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of `name`, replacing any previous value.
+    pub fn with(mut self, name: &str, value: impl AsRef<[u8]>) -> Self {
+        self.values.insert(name.into(), value.as_ref().to_vec());
+        self
+    }
+
+    /// Binds the configuration to the VM instance identified by `id`.
+    pub fn with_instance_id(self, id: Guid) -> Self {
+        self.with(INSTANCE_ID, id.0)
+    }
+
+    /// The instance ID, if set and well-formed.
+    pub fn instance_id(&self) -> Option<Guid> {
+        let mut id = [0u8; 16];
+        match self.get(INSTANCE_ID) {
+            Some(value) if value.len() == id.len() => id.copy_from_slice(value),
+            _ => return None,
+        }
+
+        Some(Guid(id))
+    }
+
+    /// The value of `name`, if set.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.values.get(name).map(|v| &v[..])
+    }
+
+    /// The SHA-256 digest of the canonical encoding, for `HOST_DATA`.
+    ///
+    /// Each name and value is prefixed with its length as a little-endian
+    /// `u32`, so no two configurations share an encoding.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(DOMAIN);
+
+        for (name, value) in &self.values {
+            hasher.update((name.len() as u32).to_le_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update((value.len() as u32).to_le_bytes());
+            hasher.update(value);
+        }
+
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.finalize());
+        digest
+    }
+
+    /// Whether the report's `HOST_DATA` is the digest of this configuration.
+    pub fn matches(&self, report: &AttestationReport) -> bool {
+        report.host_data == self.digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> VmmConfig {
+        VmmConfig::new()
+            .with("device_model", "virtio-blk,virtio-net")
+            .with("cmdline", "console=ttyS0")
+    }
+
+    #[test]
+    fn canonical() {
+        let reordered = VmmConfig::new()
+            .with("cmdline", "console=ttyS0")
+            .with("device_model", "virtio-blk,virtio-net");
+        assert_eq!(config().digest(), reordered.digest());
+
+        let changed = config().with("cmdline", "console=hvc0");
+        assert_ne!(config().digest(), changed.digest());
+        assert_eq!(changed.get("cmdline"), Some(&b"console=hvc0"[..]));
+
+        assert_ne!(
+            VmmConfig::new().with("ab", "c").digest(),
+            VmmConfig::new().with("a", "bc").digest()
+        );
+    }
+
+    #[test]
+    fn instance_id() {
+        let id: Guid = "7d2b5e5c-2f1a-4c0e-9a7e-3c1f0b6d8e42".parse().unwrap();
+        let bound = config().with_instance_id(id);
+
+        assert_eq!(bound.instance_id(), Some(id));
+        assert_eq!(config().instance_id(), None);
+        assert_ne!(bound.digest(), config().digest());
+
+        let mut report = AttestationReport::from_bytes(&crate::report::tests::example()).unwrap();
+        report.host_data = VmmConfig::new().with_instance_id(id).digest();
+        assert!(VmmConfig::new().with_instance_id(id).matches(&report));
+        assert!(!VmmConfig::new()
+            .with_instance_id(Guid::NULL)
+            .matches(&report));
+    }
+
+    #[test]
+    fn matches() {
+        let mut report = AttestationReport::from_bytes(&crate::report::tests::example()).unwrap();
+        assert!(!config().matches(&report));
+
+        report.host_data = config().digest();
+        assert!(config().matches(&report));
+        assert!(!config().with("cmdline", "").matches(&report));
+    }
+}
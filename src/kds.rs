@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Endpoints of the AMD Key Distribution Service (KDS).
+//!
+//! Reports signed by a VCEK are verified with a per-chip certificate and
+//! the `vcek` certificate chain. Reports signed by a VLEK use the `vlek`
+//! chain instead, and the VLEK certificate itself is not served by the
+//! KDS; it has to come from the cloud provider, usually through the
+//! extended report's certificate table. [`Endpoints::for_report`] picks
+//! the right set of URLs from the report's `SIGNING_KEY` field.
+
+use crate::certs::CertTable;
+use crate::report::{AttestationReport, SigningKey};
+
+use std::fmt;
+
+/// The base URL of the KDS.
+pub const BASE: &str = "https://kdsintf.amd.com";
+
+/// The processor generation, which selects the KDS product path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Product {
+    /// 3rd generation EPYC.
+    Milan,
+
+    /// 4th generation EPYC.
+    Genoa,
+}
+
+impl Product {
+    /// The name of the product in KDS URLs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Product::Milan => "Milan",
+            Product::Genoa => "Genoa",
+        }
+    }
+
+    /// Identifies the product from the CPUID fields of a version 3 report.
+    pub fn from_report(report: &AttestationReport) -> Option<Self> {
+        match (report.cpuid_fam_id, report.cpuid_mod_id) {
+            (0x19, 0x00..=0x0f) => Some(Product::Milan),
+            (0x19, 0x10..=0x1f) | (0x19, 0xa0..=0xaf) => Some(Product::Genoa),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Errors produced when routing a report to KDS endpoints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The report is not signed.
+    Unsigned,
+
+    /// The report uses a reserved `SIGNING_KEY` encoding.
+    UnknownSigningKey(u8),
+
+    /// The chip ID is masked, so the VCEK cannot be looked up.
+    ChipIdMasked,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Unsigned => write!(f, "report is not signed"),
+            Error::UnknownSigningKey(v) => write!(f, "unknown signing key {}", v),
+            Error::ChipIdMasked => write!(f, "chip ID is masked"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// A stable numeric code for the error, in the range 500-599.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::Unsigned => 500,
+            Error::UnknownSigningKey(_) => 501,
+            Error::ChipIdMasked => 502,
+        }
+    }
+}
+
+/// The KDS URLs needed to verify one report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The key that signed the report.
+    pub signing_key: SigningKey,
+
+    /// The endorsement key certificate, if the KDS serves it.
+    pub cert: Option<String>,
+
+    /// The ASK and ARK certificate chain.
+    pub cert_chain: String,
+
+    /// The certificate revocation list.
+    pub crl: String,
+}
+
+impl Endpoints {
+    /// Routes a report to the endpoints matching its signing key.
+    pub fn for_report(report: &AttestationReport, product: Product) -> Result<Self, Error> {
+        let (kind, cert) = match report.signing_key() {
+            SigningKey::Vcek => ("vcek", Some(vcek_url(report, product)?)),
+            SigningKey::Vlek => ("vlek", None),
+            SigningKey::None => return Err(Error::Unsigned),
+            SigningKey::Reserved(v) => return Err(Error::UnknownSigningKey(v)),
+        };
+
+        Ok(Self {
+            signing_key: report.signing_key(),
+            cert,
+            cert_chain: format!("{}/{}/v1/{}/cert_chain", BASE, kind, product),
+            crl: format!("{}/{}/v1/{}/crl", BASE, kind, product),
+        })
+    }
+}
+
+/// A transport for KDS requests.
+///
+/// The crate ships no HTTP client; implement this for whichever client
+/// fits the deployment, e.g. one going through a proxy or with mTLS.
+/// Closures taking a URL implement it as well.
+pub trait HttpFetch {
+    /// The error produced when a request fails.
+    type Error: std::error::Error;
+
+    /// Fetches the body of `url`.
+    fn get(&self, url: &str) -> Result<Vec<u8>, Self::Error>;
+}
+
+impl<F, E> HttpFetch for F
+where
+    F: Fn(&str) -> Result<Vec<u8>, E>,
+    E: std::error::Error,
+{
+    type Error = E;
+
+    fn get(&self, url: &str) -> Result<Vec<u8>, E> {
+        self(url)
+    }
+}
+
+/// The raw KDS responses for a set of [`Endpoints`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fetched {
+    /// The DER-encoded endorsement key certificate, if the KDS serves it.
+    pub cert: Option<Vec<u8>>,
+
+    /// The PEM-encoded ASK and ARK certificates.
+    pub cert_chain: Vec<u8>,
+
+    /// The DER-encoded certificate revocation list.
+    pub crl: Vec<u8>,
+}
+
+impl Endpoints {
+    /// Fetches every endpoint with `client`.
+    pub fn fetch<F: HttpFetch>(&self, client: &F) -> Result<Fetched, F::Error> {
+        Ok(Fetched {
+            cert: self
+                .cert
+                .as_deref()
+                .map(|url| client.get(url))
+                .transpose()?,
+            cert_chain: client.get(&self.cert_chain)?,
+            crl: client.get(&self.crl)?,
+        })
+    }
+}
+
+/// How far certificates supplied by the host are trusted.
+///
+/// Extended guest requests return endorsement key certificates chosen by
+/// the hypervisor. Trusting them blindly lets the host substitute a
+/// certificate, so by default they are only used after a cross-check, or
+/// for VLEK certificates after the caller has verified their chain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HostCerts {
+    /// Use the host's certificate only if it equals the one from the KDS.
+    /// A differing certificate is an error, as it points at a
+    /// misbehaving host. This is the default.
+    ///
+    /// The KDS does not serve VLEK certificates, so a host-supplied VLEK
+    /// certificate cannot be compared. It is returned in
+    /// [`TrustError::VerifyChain`] instead, to be used only once it is
+    /// verified against the KDS `vlek` certificate chain at
+    /// [`Endpoints::cert_chain`].
+    CrossCheck,
+
+    /// Use the host's certificate if there is one, and the KDS otherwise.
+    Accept,
+
+    /// Ignore the host's certificates and always fetch from the KDS.
+    Reject,
+}
+
+// `#[default]` on enum variants needs a newer compiler than the MSRV.
+#[allow(clippy::derivable_impls)]
+impl Default for HostCerts {
+    fn default() -> Self {
+        HostCerts::CrossCheck
+    }
+}
+
+/// Errors produced when resolving the endorsement key certificate.
+#[derive(Debug)]
+pub enum TrustError<E> {
+    /// Fetching from the KDS failed.
+    Fetch(E),
+
+    /// No acceptable source has the certificate. VLEK certificates are
+    /// only available from the host.
+    Missing,
+
+    /// The host's certificate differs from the KDS's.
+    Mismatch,
+
+    /// The host supplied this VLEK certificate, which has no KDS copy to
+    /// cross-check it against. The caller must verify that it chains to
+    /// the KDS `vlek` certificate chain before using it.
+    VerifyChain(Vec<u8>),
+}
+
+impl<E: fmt::Display> fmt::Display for TrustError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrustError::Fetch(e) => write!(f, "KDS request failed: {}", e),
+            TrustError::Missing => write!(f, "no trusted endorsement key certificate"),
+            TrustError::Mismatch => write!(f, "host certificate differs from KDS certificate"),
+            TrustError::VerifyChain(_) => write!(
+                f,
+                "host VLEK certificate must be verified against the KDS certificate chain"
+            ),
+        }
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for TrustError<E> {}
+
+impl<E> TrustError<E> {
+    /// A stable numeric code for the error, in the range 500-599.
+    pub fn code(&self) -> u32 {
+        match self {
+            TrustError::Fetch(_) => 510,
+            TrustError::Missing => 511,
+            TrustError::Mismatch => 512,
+            TrustError::VerifyChain(_) => 513,
+        }
+    }
+}
+
+impl Endpoints {
+    /// Resolves the endorsement key certificate under the `trust` policy.
+    ///
+    /// `host` is the certificate table returned with the report, if any.
+    pub fn endorsement_cert<F: HttpFetch>(
+        &self,
+        host: Option<&CertTable>,
+        trust: HostCerts,
+        client: &F,
+    ) -> Result<Vec<u8>, TrustError<F::Error>> {
+        let host = match trust {
+            HostCerts::Reject => None,
+            _ => self
+                .signing_key
+                .cert_guid()
+                .and_then(|guid| host?.get(&guid)),
+        };
+
+        if let (HostCerts::Accept, Some(cert)) = (trust, host) {
+            return Ok(cert.to_vec());
+        }
+
+        let url = match (&self.cert, host) {
+            (Some(url), _) => url,
+            (None, Some(cert)) => return Err(TrustError::VerifyChain(cert.to_vec())),
+            (None, None) => return Err(TrustError::Missing),
+        };
+        let kds = client.get(url).map_err(TrustError::Fetch)?;
+
+        match host {
+            Some(cert) if cert != &kds[..] => Err(TrustError::Mismatch),
+            _ => Ok(kds),
+        }
+    }
+}
+
+/// The URL of the VCEK certificate matching the report's chip and TCB.
+pub fn vcek_url(report: &AttestationReport, product: Product) -> Result<String, Error> {
+    let chip_id = report.chip_id().ok_or(Error::ChipIdMasked)?;
+    let tcb = report.reported_tcb;
+    Ok(format!(
+        "{}/vcek/v1/{}/{}?blSPL={:02}&teeSPL={:02}&snpSPL={:02}&ucodeSPL={:02}",
+        BASE,
+        product,
+        crate::hex::encode(chip_id),
+        tcb.bootloader,
+        tcb.tee,
+        tcb.snp,
+        tcb.microcode
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(signing_key: u32) -> AttestationReport {
+        let mut report = AttestationReport::from_bytes(&crate::report::tests::example()).unwrap();
+        report.key_info = signing_key << 2;
+        report
+    }
+
+    #[test]
+    fn vcek() {
+        let endpoints = Endpoints::for_report(&report(0), Product::Milan).unwrap();
+
+        assert_eq!(endpoints.signing_key, SigningKey::Vcek);
+        assert_eq!(
+            endpoints.cert.unwrap(),
+            format!(
+                "https://kdsintf.amd.com/vcek/v1/Milan/{}?blSPL=02&teeSPL=00&snpSPL=06&ucodeSPL=100",
+                "aa".repeat(64)
+            )
+        );
+        assert_eq!(
+            endpoints.cert_chain,
+            "https://kdsintf.amd.com/vcek/v1/Milan/cert_chain"
+        );
+        assert_eq!(endpoints.crl, "https://kdsintf.amd.com/vcek/v1/Milan/crl");
+    }
+
+    #[test]
+    fn vlek() {
+        let endpoints = Endpoints::for_report(&report(1), Product::Genoa).unwrap();
+
+        assert_eq!(endpoints.signing_key, SigningKey::Vlek);
+        assert_eq!(endpoints.cert, None);
+        assert_eq!(
+            endpoints.cert_chain,
+            "https://kdsintf.amd.com/vlek/v1/Genoa/cert_chain"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Endpoints::for_report(&report(7), Product::Milan),
+            Err(Error::Unsigned)
+        );
+        assert_eq!(
+            Endpoints::for_report(&report(2), Product::Milan),
+            Err(Error::UnknownSigningKey(2))
+        );
+
+        let mut masked = report(0);
+        masked.chip_id = [0; 64];
+        assert_eq!(
+            Endpoints::for_report(&masked, Product::Milan),
+            Err(Error::ChipIdMasked)
+        );
+    }
+
+    #[test]
+    fn fetch() {
+        let client = |url: &str| -> Result<Vec<u8>, std::io::Error> {
+            if url.ends_with("crl") {
+                return Err(std::io::ErrorKind::NotFound.into());
+            }
+            Ok(url.as_bytes().to_vec())
+        };
+
+        let mut endpoints = Endpoints::for_report(&report(1), Product::Genoa).unwrap();
+        assert!(endpoints.fetch(&client).is_err());
+
+        endpoints.crl = "chain".into();
+        let fetched = endpoints.fetch(&client).unwrap();
+        assert_eq!(fetched.cert, None);
+        assert_eq!(fetched.cert_chain, endpoints.cert_chain.as_bytes());
+        assert_eq!(fetched.crl, b"chain");
+    }
+
+    #[test]
+    fn host_certs() {
+        let kds = |_: &str| -> Result<Vec<u8>, std::io::Error> { Ok(b"kds".to_vec()) };
+        let vcek = Endpoints::for_report(&report(0), Product::Milan).unwrap();
+        let vlek = Endpoints::for_report(&report(1), Product::Milan).unwrap();
+        let host = CertTable::builder().vcek("host").vlek("vlek").table();
+        let same = CertTable::builder().vcek("kds").table();
+
+        let resolve = |endpoints: &Endpoints, host, trust| {
+            endpoints
+                .endorsement_cert(host, trust, &kds)
+                .map_err(|e| e.code())
+        };
+
+        assert_eq!(HostCerts::default(), HostCerts::CrossCheck);
+        assert_eq!(resolve(&vcek, Some(&host), HostCerts::CrossCheck), Err(512));
+        assert_eq!(
+            resolve(&vcek, Some(&same), HostCerts::CrossCheck),
+            Ok(b"kds".to_vec())
+        );
+        assert_eq!(
+            resolve(&vcek, None, HostCerts::CrossCheck),
+            Ok(b"kds".to_vec())
+        );
+        assert_eq!(
+            resolve(&vcek, Some(&host), HostCerts::Accept),
+            Ok(b"host".to_vec())
+        );
+        assert_eq!(
+            resolve(&vcek, Some(&host), HostCerts::Reject),
+            Ok(b"kds".to_vec())
+        );
+
+        assert_eq!(
+            resolve(&vlek, Some(&host), HostCerts::Accept),
+            Ok(b"vlek".to_vec())
+        );
+        assert!(matches!(
+            vlek.endorsement_cert(Some(&host), HostCerts::default(), &kds),
+            Err(TrustError::VerifyChain(cert)) if cert == b"vlek"
+        ));
+        assert_eq!(resolve(&vlek, Some(&same), HostCerts::CrossCheck), Err(511));
+        assert_eq!(resolve(&vlek, None, HostCerts::CrossCheck), Err(511));
+        assert_eq!(resolve(&vlek, Some(&host), HostCerts::Reject), Err(511));
+    }
+
+    #[test]
+    fn product() {
+        let mut report = report(0);
+        assert_eq!(Product::from_report(&report), Some(Product::Milan));
+
+        report.cpuid_mod_id = 0x11;
+        assert_eq!(Product::from_report(&report), Some(Product::Genoa));
+
+        report.cpuid_fam_id = 0;
+        assert_eq!(Product::from_report(&report), None);
+    }
+}
@@ -0,0 +1,457 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structures consumed by the SNP firmware while launching a guest.
+
+use crate::redact::Sensitive;
+use crate::spec::policy as spec;
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Errors produced when decoding launch structures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The input did not have the exact size required by the specification.
+    InvalidLength {
+        /// The size mandated by the specification.
+        expected: usize,
+        /// The size of the input.
+        actual: usize,
+    },
+
+    /// The structure carries a version this crate does not understand.
+    UnsupportedVersion(u32),
+
+    /// These reserved guest policy bits do not have their mandated value.
+    ReservedPolicyBits(u64),
+
+    /// These reserved VMPL permission bits are set.
+    ReservedPermissionBits(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidLength { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            Error::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            Error::ReservedPolicyBits(bits) => {
+                write!(f, "reserved policy bits {:#x} have the wrong value", bits)
+            }
+            Error::ReservedPermissionBits(bits) => {
+                write!(f, "reserved permission bits {:#x} are set", bits)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// A stable numeric code for the error, in the range 200-299.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::InvalidLength { .. } => 200,
+            Error::UnsupportedVersion(_) => 201,
+            Error::ReservedPolicyBits(_) => 202,
+            Error::ReservedPermissionBits(_) => 203,
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// The flags of the guest policy.
+    ///
+    /// See "Guest Policy Structure" in the SEV-SNP firmware ABI
+    /// specification. Bit 17 must always be one and is set by
+    /// [`Policy::to_u64`] rather than being a flag.
+    #[derive(Default)]
+    pub struct PolicyFlags: u64 {
+        /// SMT is allowed.
+        const SMT = 1 << spec::SMT;
+
+        /// Association with a migration agent is allowed.
+        const MIGRATE_MA = 1 << spec::MIGRATE_MA;
+
+        /// Debugging is allowed.
+        const DEBUG = 1 << spec::DEBUG;
+
+        /// The guest may only be activated on one socket.
+        const SINGLE_SOCKET = 1 << spec::SINGLE_SOCKET;
+
+        /// CXL can be populated with devices or memory.
+        const CXL_ALLOW = 1 << spec::CXL_ALLOW;
+
+        /// AES-256-XTS is required for memory encryption.
+        const MEM_AES_256_XTS = 1 << spec::MEM_AES_256_XTS;
+
+        /// Running Average Power Limit must be disabled.
+        const RAPL_DIS = 1 << spec::RAPL_DIS;
+
+        /// Ciphertext hiding must be enabled.
+        const CIPHERTEXT_HIDING = 1 << spec::CIPHERTEXT_HIDING;
+
+        /// Guest page swap commands are disallowed.
+        const PAGE_SWAP_DISABLE = 1 << spec::PAGE_SWAP_DISABLE;
+    }
+}
+
+/// The guest policy passed to `SNP_LAUNCH_START`.
+///
+/// The firmware refuses to launch the guest if it cannot honor the
+/// policy, and reports it in every attestation report.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Policy {
+    /// The policy flags.
+    pub flags: PolicyFlags,
+
+    /// The minimum major version of the firmware ABI.
+    pub abi_major: u8,
+
+    /// The minimum minor version of the firmware ABI.
+    pub abi_minor: u8,
+}
+
+impl Policy {
+    /// Encodes the policy as the firmware expects it.
+    pub fn to_u64(&self) -> u64 {
+        u64::from(self.abi_minor) << spec::ABI_MINOR
+            | u64::from(self.abi_major) << spec::ABI_MAJOR
+            | 1 << spec::RESERVED_MBO
+            | self.flags.bits()
+    }
+}
+
+impl TryFrom<u64> for Policy {
+    type Error = Error;
+
+    /// Decodes a policy, e.g. from an attestation report.
+    ///
+    /// Fails if an undefined bit is set or bit 17 is clear.
+    fn try_from(value: u64) -> Result<Self, Error> {
+        let mbo = 1 << spec::RESERVED_MBO;
+        let defined = 0xffff | mbo | PolicyFlags::all().bits();
+
+        let wrong = value & !defined | !value & mbo;
+        if wrong != 0 {
+            return Err(Error::ReservedPolicyBits(wrong));
+        }
+
+        Ok(Self {
+            flags: PolicyFlags::from_bits_truncate(value),
+            abi_major: (value >> spec::ABI_MAJOR) as u8,
+            abi_minor: (value >> spec::ABI_MINOR) as u8,
+        })
+    }
+}
+
+impl From<Policy> for u64 {
+    fn from(policy: Policy) -> Self {
+        policy.to_u64()
+    }
+}
+
+bitflags::bitflags! {
+    /// The permissions a VMPL is granted on a page.
+    ///
+    /// `SNP_LAUNCH_UPDATE` takes one such mask for each of VMPL1 to VMPL3
+    /// (see "VMPL Permission Mask" in the SEV-SNP firmware ABI
+    /// specification). VMPL0 always has full access.
+    #[derive(Default)]
+    pub struct VmplPerms: u8 {
+        /// The page may be read.
+        const READ = 1 << 0;
+
+        /// The page may be written.
+        const WRITE = 1 << 1;
+
+        /// The page may be executed in user mode.
+        const EXECUTE_USER = 1 << 2;
+
+        /// The page may be executed in supervisor mode.
+        const EXECUTE_SUPERVISOR = 1 << 3;
+    }
+}
+
+impl From<VmplPerms> for u8 {
+    fn from(perms: VmplPerms) -> Self {
+        perms.bits()
+    }
+}
+
+impl TryFrom<u8> for VmplPerms {
+    type Error = Error;
+
+    /// Decodes a raw mask, failing if a reserved bit is set.
+    fn try_from(mask: u8) -> Result<Self, Error> {
+        Self::from_bits(mask).ok_or(Error::ReservedPermissionBits(mask & !Self::all().bits()))
+    }
+}
+
+/// The ID block optionally passed to `SNP_LAUNCH_FINISH`.
+///
+/// The firmware compares its fields against the launched guest and fails
+/// the launch on any mismatch, so the encoding has to be byte-exact. All
+/// multi-byte fields are little-endian (see "ID Block" in the SEV-SNP
+/// firmware ABI specification).
+///
+/// | Offset | Size | Field       |
+/// |--------|------|-------------|
+/// | 0x00   | 48   | `LD`        |
+/// | 0x30   | 16   | `FAMILY_ID` |
+/// | 0x40   | 16   | `IMAGE_ID`  |
+/// | 0x50   | 4    | `VERSION`   |
+/// | 0x54   | 4    | `GUEST_SVN` |
+/// | 0x58   | 8    | `POLICY`    |
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct IdBlock {
+    /// The expected launch digest of the guest.
+    pub ld: [u8; 48],
+
+    /// Family of the guest, as chosen by the guest owner.
+    pub family_id: [u8; 16],
+
+    /// Image of the guest, as chosen by the guest owner.
+    pub image_id: [u8; 16],
+
+    /// Security version number of the guest.
+    pub guest_svn: u32,
+
+    /// The guest policy the guest must be launched with.
+    pub policy: u64,
+}
+
+impl fmt::Debug for IdBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IdBlock")
+            .field("ld", &Sensitive(&self.ld))
+            .field("family_id", &Sensitive(&self.family_id))
+            .field("image_id", &Sensitive(&self.image_id))
+            .field("guest_svn", &self.guest_svn)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl IdBlock {
+    /// Size of the encoded ID block.
+    pub const SIZE: usize = 96;
+
+    /// The only ID block version defined by the specification.
+    pub const VERSION: u32 = 1;
+
+    const LD: usize = 0x00;
+    const FAMILY_ID: usize = 0x30;
+    const IMAGE_ID: usize = 0x40;
+    const VERSION_OFFSET: usize = 0x50;
+    const GUEST_SVN: usize = 0x54;
+    const POLICY: usize = 0x58;
+
+    /// Creates an ID block for the given launch digest and policy.
+    ///
+    /// The family ID, image ID and guest SVN start out as zero.
+    pub fn new(ld: [u8; 48], policy: u64) -> Self {
+        Self {
+            ld,
+            family_id: [0; 16],
+            image_id: [0; 16],
+            guest_svn: 0,
+            policy,
+        }
+    }
+
+    /// Sets the family ID.
+    pub fn with_family_id(mut self, family_id: [u8; 16]) -> Self {
+        self.family_id = family_id;
+        self
+    }
+
+    /// Sets the image ID.
+    pub fn with_image_id(mut self, image_id: [u8; 16]) -> Self {
+        self.image_id = image_id;
+        self
+    }
+
+    /// Sets the guest SVN.
+    ///
+    /// The SVN is reported in the attestation report, where it can be
+    /// checked with [`AppraisalPolicy::min_guest_svn`] to prevent the guest
+    /// image from being rolled back.
+    ///
+    /// [`AppraisalPolicy::min_guest_svn`]: crate::appraisal::AppraisalPolicy::min_guest_svn
+    pub fn with_guest_svn(mut self, guest_svn: u32) -> Self {
+        self.guest_svn = guest_svn;
+        self
+    }
+
+    /// Encodes the ID block in the layout expected by the firmware.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+
+        bytes[Self::LD..Self::FAMILY_ID].copy_from_slice(&self.ld);
+        bytes[Self::FAMILY_ID..Self::IMAGE_ID].copy_from_slice(&self.family_id);
+        bytes[Self::IMAGE_ID..Self::VERSION_OFFSET].copy_from_slice(&self.image_id);
+        bytes[Self::VERSION_OFFSET..Self::GUEST_SVN].copy_from_slice(&Self::VERSION.to_le_bytes());
+        bytes[Self::GUEST_SVN..Self::POLICY].copy_from_slice(&self.guest_svn.to_le_bytes());
+        bytes[Self::POLICY..Self::SIZE].copy_from_slice(&self.policy.to_le_bytes());
+
+        bytes
+    }
+
+    /// Decodes an ID block, rejecting inputs of the wrong size or version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != Self::SIZE {
+            return Err(Error::InvalidLength {
+                expected: Self::SIZE,
+                actual: bytes.len(),
+            });
+        }
+
+        let version = u32::from_le_bytes(le(&bytes[Self::VERSION_OFFSET..Self::GUEST_SVN]));
+        if version != Self::VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let mut ld = [0u8; 48];
+        let mut family_id = [0u8; 16];
+        let mut image_id = [0u8; 16];
+        ld.copy_from_slice(&bytes[Self::LD..Self::FAMILY_ID]);
+        family_id.copy_from_slice(&bytes[Self::FAMILY_ID..Self::IMAGE_ID]);
+        image_id.copy_from_slice(&bytes[Self::IMAGE_ID..Self::VERSION_OFFSET]);
+
+        Ok(Self {
+            ld,
+            family_id,
+            image_id,
+            guest_svn: u32::from_le_bytes(le(&bytes[Self::GUEST_SVN..Self::POLICY])),
+            policy: u64::from_le_bytes(le(&bytes[Self::POLICY..Self::SIZE])),
+        })
+    }
+}
+
+crate::static_assert!(IdBlock::POLICY + 8 == IdBlock::SIZE);
+
+fn le<T: Default + AsMut<[u8]>>(bytes: &[u8]) -> T {
+    let mut out = T::default();
+    out.as_mut().copy_from_slice(bytes);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> IdBlock {
+        let mut ld = [0u8; 48];
+        for (i, b) in ld.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        IdBlock::new(ld, 0x0000_0000_0003_001f)
+            .with_family_id([0xf0; 16])
+            .with_image_id([0x1e; 16])
+            .with_guest_svn(0x0102_0304)
+    }
+
+    #[test]
+    fn encoding() {
+        let bytes = example().to_bytes();
+
+        assert_eq!(bytes.len(), 96);
+        assert_eq!(&bytes[0x00..0x30], &example().ld[..]);
+        assert_eq!(&bytes[0x30..0x40], &[0xf0; 16]);
+        assert_eq!(&bytes[0x40..0x50], &[0x1e; 16]);
+        assert_eq!(&bytes[0x50..0x54], &[0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(&bytes[0x54..0x58], &[0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(
+            &bytes[0x58..0x60],
+            &[0x1f, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn policy() {
+        assert_eq!(Policy::default().to_u64(), 0x2_0000);
+
+        let policy = Policy {
+            flags: PolicyFlags::SMT | PolicyFlags::SINGLE_SOCKET | PolicyFlags::PAGE_SWAP_DISABLE,
+            abi_major: 1,
+            abi_minor: 55,
+        };
+        assert_eq!(u64::from(policy), 0x0213_0137);
+
+        assert_eq!(PolicyFlags::all().bits(), 0x03fd_0000);
+    }
+
+    #[test]
+    fn policy_decoding() {
+        let policy = Policy {
+            flags: PolicyFlags::DEBUG | PolicyFlags::CIPHERTEXT_HIDING,
+            abi_major: 1,
+            abi_minor: 51,
+        };
+        assert_eq!(Policy::try_from(policy.to_u64()), Ok(policy));
+
+        let report =
+            crate::report::AttestationReport::from_bytes(&crate::report::tests::example()).unwrap();
+        assert_eq!(
+            Policy::try_from(report.policy),
+            Ok(Policy {
+                flags: PolicyFlags::SMT,
+                ..Policy::default()
+            })
+        );
+
+        assert_eq!(
+            Policy::try_from(0),
+            Err(Error::ReservedPolicyBits(0x2_0000))
+        );
+        assert_eq!(
+            Policy::try_from(1 << 63 | 1 << 26 | 0x2_0000),
+            Err(Error::ReservedPolicyBits(1 << 63 | 1 << 26))
+        );
+    }
+
+    #[test]
+    fn vmpl_perms() {
+        let perms = VmplPerms::READ | VmplPerms::EXECUTE_SUPERVISOR;
+        assert_eq!(u8::from(perms), 0b1001);
+        assert_eq!(VmplPerms::try_from(0b1001), Ok(perms));
+        assert_eq!(VmplPerms::try_from(0xf), Ok(VmplPerms::all()));
+        assert_eq!(
+            VmplPerms::try_from(0x31),
+            Err(Error::ReservedPermissionBits(0x30))
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let block = example();
+        assert_eq!(IdBlock::from_bytes(&block.to_bytes()), Ok(block));
+    }
+
+    #[test]
+    fn invalid_length() {
+        let bytes = example().to_bytes();
+
+        assert_eq!(
+            IdBlock::from_bytes(&bytes[..95]),
+            Err(Error::InvalidLength {
+                expected: 96,
+                actual: 95
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_version() {
+        let mut bytes = example().to_bytes();
+        bytes[0x50] = 2;
+
+        assert_eq!(
+            IdBlock::from_bytes(&bytes),
+            Err(Error::UnsupportedVersion(2))
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compile-time layout assertions for ABI structures.
+//!
+//! The firmware interfaces are defined in terms of exact sizes and
+//! offsets. These macros turn a mismatch into a build failure, so crates
+//! defining their own SNP-adjacent structures can get the same guarantees
+//! this crate relies on.
+
+/// Fails the build unless the constant expression is true.
+///
+/// ```
+/// snp::static_assert!(snp::launch::IdBlock::SIZE == 96);
+/// ```
+#[macro_export]
+macro_rules! static_assert {
+    ($cond:expr $(,)?) => {
+        const _: [(); 0 - !{
+            const ASSERT: bool = $cond;
+            ASSERT
+        } as usize] = [];
+    };
+}
+
+/// Fails the build unless the type has the given size in bytes.
+///
+/// ```
+/// #[repr(C)]
+/// struct Entry {
+///     guid: [u8; 16],
+///     offset: u32,
+///     length: u32,
+/// }
+///
+/// snp::static_assert_size!(Entry, 24);
+/// ```
+#[macro_export]
+macro_rules! static_assert_size {
+    ($ty:ty, $size:expr $(,)?) => {
+        const _: [(); $size] = [(); ::core::mem::size_of::<$ty>()];
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[repr(C)]
+    struct Entry {
+        _guid: [u8; 16],
+        _offset: u32,
+        _length: u32,
+    }
+
+    #[test]
+    fn expand() {
+        crate::static_assert!(crate::secrets::SecretsPage::SIZE == 4096);
+        crate::static_assert_size!(Entry, 24);
+    }
+}
@@ -1,9 +1,58 @@
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod addr;
+pub mod appraisal;
+pub mod certs;
+pub mod claims;
+pub mod cpuid;
+pub mod freshness;
+pub mod guest;
+mod hex;
+pub mod host_data;
+pub mod kds;
+pub mod launch;
+pub mod layout;
+pub mod parse;
+pub mod prelude;
+pub mod redact;
+pub mod release;
+pub mod report;
+pub mod report_data;
+pub mod secrets;
+#[cfg(feature = "serde")]
+pub mod serde_hex;
+pub mod spec;
+
 #[cfg(test)]
 mod tests {
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn error_codes() {
+        use crate::parse::ErrorKind;
+
+        let codes = [
+            ErrorKind::Truncated { needed: 1 }.code(),
+            ErrorKind::NonZeroReserved.code(),
+            crate::launch::Error::UnsupportedVersion(0).code(),
+            crate::certs::BuildError::TooLarge.code(),
+            crate::certs::ParseGuidError.code(),
+            crate::report_data::Error::NonZeroPadding.code(),
+            crate::kds::Error::ChipIdMasked.code(),
+            crate::guest::tsm::Error::Conflict.code(),
+            crate::appraisal::Failure::ImageId.code(),
+            crate::release::Denied::ChannelBinding.code(),
+            crate::freshness::Error::Future.code(),
+            crate::addr::Unaligned(crate::addr::Gpa(1)).code(),
+            crate::cpuid::ReadError::Unaligned(crate::addr::Unaligned(crate::addr::Gpa(1))).code(),
+        ];
+
+        assert_eq!(
+            codes,
+            [100, 105, 201, 302, 310, 407, 502, 602, 710, 801, 903, 1000, 1100]
+        );
+    }
 }
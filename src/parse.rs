@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Errors and helpers for decoding structures from untrusted bytes.
+//!
+//! All parsers in this crate operate on byte slices supplied by the
+//! hypervisor, the firmware or a remote party. They never panic on
+//! malformed input; instead they return an [`Error`] that records the
+//! offset at which decoding failed.
+//!
+//! Reserved fields are ignored by default. Parsers also come in a strict
+//! flavor, e.g.
+//! [`AttestationReport::from_bytes_strict`](crate::report::AttestationReport::from_bytes_strict),
+//! which rejects reserved fields that are not zero with
+//! [`ErrorKind::NonZeroReserved`]. This catches ABI drift and malformed
+//! evidence early, at the cost of failing on firmware that starts using
+//! a field this crate does not know about yet.
+
+use std::fmt;
+
+/// The reason decoding failed.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input ended before the field at the error offset was complete.
+    Truncated {
+        /// The number of bytes the field required.
+        needed: usize,
+    },
+
+    /// The input continues past the end of the structure.
+    TrailingBytes,
+
+    /// The structure carries a version this crate does not understand.
+    UnsupportedVersion(u32),
+
+    /// A field has a value the specification does not allow.
+    InvalidValue,
+
+    /// A field refers to data outside of the input.
+    OutOfBounds,
+
+    /// A reserved field is not zero. Only reported by strict parsers.
+    NonZeroReserved,
+}
+
+impl ErrorKind {
+    /// A stable numeric code for the kind, in the range 100-199.
+    ///
+    /// Codes never change meaning, so callers outside of Rust can branch
+    /// on them instead of matching error messages.
+    pub fn code(&self) -> u32 {
+        match self {
+            ErrorKind::Truncated { .. } => 100,
+            ErrorKind::TrailingBytes => 101,
+            ErrorKind::UnsupportedVersion(_) => 102,
+            ErrorKind::InvalidValue => 103,
+            ErrorKind::OutOfBounds => 104,
+            ErrorKind::NonZeroReserved => 105,
+        }
+    }
+}
+
+/// An error produced while decoding untrusted bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    offset: usize,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub(crate) fn new(offset: usize, kind: ErrorKind) -> Self {
+        Self { offset, kind }
+    }
+
+    /// The byte offset into the input at which decoding failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The reason decoding failed.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The stable numeric code of the [kind](ErrorKind::code).
+    pub fn code(&self) -> u32 {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Truncated { needed } => write!(
+                f,
+                "input truncated at offset {:#x} ({} bytes needed)",
+                self.offset, needed
+            ),
+            ErrorKind::TrailingBytes => write!(f, "trailing bytes at offset {:#x}", self.offset),
+            ErrorKind::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {} at offset {:#x}", v, self.offset)
+            }
+            ErrorKind::InvalidValue => write!(f, "invalid value at offset {:#x}", self.offset),
+            ErrorKind::OutOfBounds => {
+                write!(f, "reference out of bounds at offset {:#x}", self.offset)
+            }
+            ErrorKind::NonZeroReserved => {
+                write!(f, "non-zero reserved byte at offset {:#x}", self.offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A bounds-checked little-endian cursor over untrusted bytes.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    strict: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_strict(bytes, false)
+    }
+
+    /// A reader that also checks reserved fields, if `strict` is set.
+    pub fn with_strict(bytes: &'a [u8], strict: bool) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            strict,
+        }
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn error(&self, kind: ErrorKind) -> Error {
+        Error::new(self.offset, kind)
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let remaining = self.bytes.len() - self.offset;
+        if remaining < len {
+            return Err(self.error(ErrorKind::Truncated { needed: len }));
+        }
+
+        let bytes = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(|_| ())
+    }
+
+    /// Skips a reserved field, which must be zero in strict mode.
+    pub fn reserved(&mut self, len: usize) -> Result<(), Error> {
+        let start = self.offset;
+        let bytes = self.bytes(len)?;
+
+        if self.strict {
+            if let Some(i) = bytes.iter().position(|b| *b != 0) {
+                return Err(Error::new(start + i, ErrorKind::NonZeroReserved));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        self.array().map(u8::from_le_bytes)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, Error> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Fails if any input is left after the structure.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.offset != self.bytes.len() {
+            return Err(self.error(ErrorKind::TrailingBytes));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader() {
+        let mut reader = Reader::new(&[1, 2, 0, 3, 0, 0, 0]);
+
+        assert_eq!(reader.u8(), Ok(1));
+        assert_eq!(reader.u16(), Ok(2));
+        assert_eq!(reader.u32(), Ok(3));
+        assert_eq!(reader.offset(), 7);
+        assert!(reader.finish().is_ok());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut reader = Reader::new(&[1, 2, 3]);
+
+        assert_eq!(reader.u8(), Ok(1));
+        assert_eq!(
+            reader.u32(),
+            Err(Error::new(1, ErrorKind::Truncated { needed: 4 }))
+        );
+    }
+
+    #[test]
+    fn reserved() {
+        let bytes = [0, 0, 1, 0];
+
+        assert!(Reader::new(&bytes).reserved(4).is_ok());
+        assert!(Reader::with_strict(&bytes, true).reserved(2).is_ok());
+        assert_eq!(
+            Reader::with_strict(&bytes, true).reserved(4),
+            Err(Error::new(2, ErrorKind::NonZeroReserved))
+        );
+    }
+
+    #[test]
+    fn trailing() {
+        let mut reader = Reader::new(&[1, 2, 3]);
+
+        assert_eq!(reader.u16(), Ok(0x0201));
+        assert_eq!(
+            reader.finish(),
+            Err(Error::new(2, ErrorKind::TrailingBytes))
+        );
+    }
+}
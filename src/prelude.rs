@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Versioned re-exports of the stable API.
+//!
+//! `use snp::prelude::v1::*;` brings the commonly used types into scope
+//! under names that stay put when the modules behind them are
+//! reorganized. Items are only ever added to a prelude version; removing
+//! or renaming one means starting a new version.
+
+/// The first stable surface of the crate.
+pub mod v1 {
+    pub use crate::addr::{Gfn, Gpa};
+    pub use crate::appraisal::{Appraisal, AppraisalPolicy, Failure};
+    pub use crate::certs::{CertTable, CertTableBuilder, Guid};
+    pub use crate::claims::Claims;
+    pub use crate::cpuid::CpuidPage;
+    pub use crate::freshness::{Challenge, Window};
+    pub use crate::guest::key::DerivedKey;
+    pub use crate::guest::tsm::Tsm;
+    pub use crate::guest::{Evidence, EvidenceProvider, Provider};
+    pub use crate::host_data::VmmConfig;
+    pub use crate::kds::{Endpoints, HostCerts, HttpFetch, Product};
+    pub use crate::launch::{IdBlock, Policy, PolicyFlags, VmplPerms};
+    pub use crate::release::KeyRelease;
+    pub use crate::report::{AttestationReport, PlatformInfo, SigningKey, TcbVersion};
+    pub use crate::secrets::SecretsPage;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::v1::*;
+
+    #[test]
+    fn v1() {
+        let report = AttestationReport::from_bytes(&crate::report::tests::example()).unwrap();
+        assert_eq!(report.signing_key(), SigningKey::Vlek);
+        assert_eq!(Guid::NULL, crate::certs::Guid::NULL);
+        assert!(AppraisalPolicy::default().appraise(&report).passed());
+    }
+}
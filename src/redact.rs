@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Control over how sensitive values appear in `Debug` output.
+//!
+//! Digests, chip IDs and other report fields can identify hardware or
+//! tenants. Applications that must keep them out of their logs can select
+//! a stricter [`Redaction`] once at startup with [`set_policy`]; all types
+//! in this crate honor it when formatted with `{:?}`, as does the
+//! alternate `Display` form of reports, `{:#}`.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How sensitive byte fields are rendered by `Debug` implementations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Redaction {
+    /// Render the full value as hex. This is the default.
+    Full,
+
+    /// Render only the first four bytes as hex.
+    Truncated,
+
+    /// Do not render the value at all.
+    Redacted,
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(0);
+
+impl Redaction {
+    fn to_u8(self) -> u8 {
+        match self {
+            Redaction::Full => 0,
+            Redaction::Truncated => 1,
+            Redaction::Redacted => 2,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Redaction::Full,
+            1 => Redaction::Truncated,
+            _ => Redaction::Redacted,
+        }
+    }
+
+    /// Formats `bytes` under this policy.
+    fn render(self, f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+        let shown = match self {
+            Redaction::Full => bytes,
+            Redaction::Truncated => &bytes[..bytes.len().min(4)],
+            Redaction::Redacted => return f.write_str("<redacted>"),
+        };
+
+        f.write_str(&crate::hex::encode(shown))?;
+
+        if shown.len() < bytes.len() {
+            f.write_str("...")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Sets the crate-wide redaction policy.
+pub fn set_policy(redaction: Redaction) {
+    POLICY.store(redaction.to_u8(), Ordering::Relaxed);
+}
+
+/// Returns the crate-wide redaction policy.
+pub fn policy() -> Redaction {
+    Redaction::from_u8(POLICY.load(Ordering::Relaxed))
+}
+
+/// Formats a sensitive byte field according to the current policy.
+pub(crate) struct Sensitive<'a>(pub &'a [u8]);
+
+impl fmt::Debug for Sensitive<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        policy().render(f, self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests must not call `set_policy`: the policy is shared by every
+    // test formatting a report in parallel.
+    struct Under<'a>(Redaction, &'a [u8]);
+
+    impl fmt::Debug for Under<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.render(f, self.1)
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let value = &[0xde, 0xad, 0xbe, 0xef, 0x01];
+        assert_eq!(policy(), Redaction::Full);
+        assert_eq!(format!("{:?}", Sensitive(value)), "deadbeef01");
+
+        assert_eq!(format!("{:?}", Under(Redaction::Full, value)), "deadbeef01");
+        assert_eq!(
+            format!("{:?}", Under(Redaction::Truncated, value)),
+            "deadbeef..."
+        );
+        assert_eq!(format!("{:?}", Under(Redaction::Truncated, &[0xab])), "ab");
+        assert_eq!(
+            format!("{:?}", Under(Redaction::Redacted, value)),
+            "<redacted>"
+        );
+    }
+
+    #[test]
+    fn encoding() {
+        for redaction in [Redaction::Full, Redaction::Truncated, Redaction::Redacted].iter() {
+            assert_eq!(Redaction::from_u8(redaction.to_u8()), *redaction);
+        }
+    }
+}
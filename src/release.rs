@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Gating the release of secrets on attestation.
+//!
+//! A [`KeyRelease`] holds a secret, for example a wrapped disk key, and
+//! only hands it out for a report that passes appraisal and whose
+//! `REPORT_DATA` carries the requester's channel binding. This is the core
+//! decision of a simple key broker.
+
+use crate::appraisal::{Appraisal, AppraisalPolicy};
+use crate::report::AttestationReport;
+
+use std::fmt;
+
+/// The reason a secret was not released.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Denied {
+    /// The report failed appraisal.
+    Appraisal(Appraisal),
+
+    /// `REPORT_DATA` does not match the channel binding.
+    ChannelBinding,
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Denied::Appraisal(appraisal) => {
+                write!(f, "appraisal failed")?;
+                for failure in &appraisal.failures {
+                    write!(f, "; {}", failure)?;
+                }
+                Ok(())
+            }
+            Denied::ChannelBinding => write!(f, "report data does not match channel binding"),
+        }
+    }
+}
+
+impl std::error::Error for Denied {}
+
+impl Denied {
+    /// A stable numeric code for the denial, in the range 800-899.
+    ///
+    /// The individual appraisal failures carry their own
+    /// [codes](crate::appraisal::Failure::code).
+    pub fn code(&self) -> u32 {
+        match self {
+            Denied::Appraisal(_) => 800,
+            Denied::ChannelBinding => 801,
+        }
+    }
+}
+
+/// A secret released only to appraised and bound reports.
+pub struct KeyRelease<T> {
+    policy: AppraisalPolicy,
+    secret: T,
+}
+
+impl<T> fmt::Debug for KeyRelease<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyRelease")
+            .field("policy", &self.policy)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+impl<T> KeyRelease<T> {
+    /// Guards `secret` with the given appraisal policy.
+    pub fn new(policy: AppraisalPolicy, secret: T) -> Self {
+        Self { policy, secret }
+    }
+
+    /// The policy reports must satisfy.
+    pub fn policy(&self) -> &AppraisalPolicy {
+        &self.policy
+    }
+
+    /// Releases the secret if `report` passes appraisal and its
+    /// `REPORT_DATA` equals `binding`.
+    ///
+    /// `binding` is what the requester's report data must be for this
+    /// channel, e.g. a nonce and a hash of its public key laid out with
+    /// a [`report_data::Schema`](crate::report_data::Schema). The report
+    /// signature must have been verified by the caller.
+    pub fn release(&self, report: &AttestationReport, binding: &[u8; 64]) -> Result<&T, Denied> {
+        let appraisal = self.policy.appraise(report);
+        if !appraisal.passed() {
+            return Err(Denied::Appraisal(appraisal));
+        }
+
+        let diff = report
+            .report_data
+            .iter()
+            .zip(binding.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return Err(Denied::ChannelBinding);
+        }
+
+        Ok(&self.secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::appraisal::Failure;
+
+    fn report() -> AttestationReport {
+        AttestationReport::from_bytes(&crate::report::tests::example()).unwrap()
+    }
+
+    #[test]
+    fn release() {
+        let release = KeyRelease::new(AppraisalPolicy::default(), "secret");
+
+        assert_eq!(release.release(&report(), &[0x33; 64]), Ok(&"secret"));
+        assert_eq!(
+            release.release(&report(), &[0x34; 64]),
+            Err(Denied::ChannelBinding)
+        );
+        assert!(!format!("{:?}", release).contains("secret\""));
+    }
+
+    #[test]
+    fn denied() {
+        let policy = AppraisalPolicy {
+            measurements: vec![[0; 48]],
+            ..Default::default()
+        };
+        let release = KeyRelease::new(policy, [0u8; 32]);
+
+        assert_eq!(
+            release.release(&report(), &[0x33; 64]),
+            Err(Denied::Appraisal(Appraisal {
+                failures: vec![Failure::Measurement]
+            }))
+        );
+    }
+}
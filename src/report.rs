@@ -0,0 +1,920 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The attestation report produced by the SNP firmware.
+
+use crate::certs::Guid;
+use crate::parse::{Error, ErrorKind, Reader};
+use crate::redact::Sensitive;
+
+use std::fmt;
+
+/// The security version numbers of the platform's firmware components.
+///
+/// See "TCB_VERSION Structure" in the SEV-SNP firmware ABI specification.
+/// The alternate `Display` form, `{:#}`, annotates each SVN with its byte
+/// in that structure.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TcbVersion {
+    /// SVN of the PSP bootloader.
+    pub bootloader: u8,
+
+    /// SVN of the PSP operating system.
+    pub tee: u8,
+
+    /// SVN of the SNP firmware.
+    pub snp: u8,
+
+    /// Lowest current patch level of all the cores.
+    pub microcode: u8,
+}
+
+impl fmt::Display for TcbVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return write!(
+                f,
+                "bootloader {} [byte 0], tee {} [byte 1], snp {} [byte 6], microcode {} [byte 7] \
+                 (TCB_VERSION Structure)",
+                self.bootloader, self.tee, self.snp, self.microcode
+            );
+        }
+
+        write!(
+            f,
+            "bootloader {}, tee {}, snp {}, microcode {}",
+            self.bootloader, self.tee, self.snp, self.microcode
+        )
+    }
+}
+
+impl From<u64> for TcbVersion {
+    fn from(value: u64) -> Self {
+        let bytes = value.to_le_bytes();
+
+        Self {
+            bootloader: bytes[0],
+            tee: bytes[1],
+            snp: bytes[6],
+            microcode: bytes[7],
+        }
+    }
+}
+
+impl From<TcbVersion> for u64 {
+    fn from(tcb: TcbVersion) -> Self {
+        u64::from_le_bytes([tcb.bootloader, tcb.tee, 0, 0, 0, 0, tcb.snp, tcb.microcode])
+    }
+}
+
+/// A firmware version as reported in the attestation report.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    /// The major version number.
+    pub major: u8,
+
+    /// The minor version number.
+    pub minor: u8,
+
+    /// The build number.
+    pub build: u8,
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)
+    }
+}
+
+impl FirmwareVersion {
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let build = reader.u8()?;
+        let minor = reader.u8()?;
+        let major = reader.u8()?;
+        reader.reserved(1)?;
+
+        Ok(Self {
+            major,
+            minor,
+            build,
+        })
+    }
+}
+
+/// The key that signed an attestation report.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SigningKey {
+    /// The Versioned Chip Endorsement Key.
+    Vcek,
+
+    /// The Versioned Loaded Endorsement Key.
+    Vlek,
+
+    /// The report is not signed.
+    None,
+
+    /// An encoding reserved by the specification.
+    Reserved(u8),
+}
+
+impl SigningKey {
+    /// The certificate table entry holding the certificate of this key.
+    pub fn cert_guid(self) -> Option<Guid> {
+        match self {
+            SigningKey::Vcek => Some(Guid::VCEK),
+            SigningKey::Vlek => Some(Guid::VLEK),
+            _ => None,
+        }
+    }
+}
+
+impl From<u8> for SigningKey {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => SigningKey::Vcek,
+            1 => SigningKey::Vlek,
+            7 => SigningKey::None,
+            v => SigningKey::Reserved(v),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Host platform properties reported in `PLATFORM_INFO`.
+    #[derive(Default)]
+    pub struct PlatformInfo: u64 {
+        /// Simultaneous multithreading is enabled.
+        const SMT_EN = 1 << 0;
+
+        /// Transparent SME is enabled.
+        const TSME_EN = 1 << 1;
+
+        /// The platform is using error correcting codes for memory.
+        const ECC_EN = 1 << 2;
+
+        /// RAPL is disabled.
+        const RAPL_DIS = 1 << 3;
+
+        /// Ciphertext hiding is enabled.
+        const CIPHERTEXT_HIDING_EN = 1 << 4;
+    }
+}
+
+/// An ECDSA P-384 signature with little-endian `R` and `S` components.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// The `R` component, zero-extended to 72 bytes.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub r: [u8; 72],
+
+    /// The `S` component, zero-extended to 72 bytes.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub s: [u8; 72],
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Signature")
+            .field("r", &Sensitive(&self.r))
+            .field("s", &Sensitive(&self.s))
+            .finish()
+    }
+}
+
+impl Signature {
+    /// Size of the encoded signature, including its reserved tail.
+    pub const SIZE: usize = 0x200;
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let r = reader.array()?;
+        let s = reader.array()?;
+        reader.reserved(Self::SIZE - 2 * 72)?;
+
+        Ok(Self { r, s })
+    }
+}
+
+/// An attestation report returned by `MSG_REPORT_REQ`.
+///
+/// See "ATTESTATION_REPORT Structure" in the SEV-SNP firmware ABI
+/// specification. Reserved fields are not retained.
+///
+/// `Display` gives a one-line summary. The alternate form, `{:#}`, lists
+/// every field on its own line with its offset and specification name, to
+/// be read side by side with the specification.
+///
+/// Identifiers and digests are rendered according to the crate's
+/// [redaction policy](crate::redact) in the `Debug` and alternate
+/// `Display` output.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct AttestationReport {
+    /// Version of the report format.
+    pub version: u32,
+
+    /// Guest SVN from the ID block.
+    pub guest_svn: u32,
+
+    /// The guest policy.
+    pub policy: u64,
+
+    /// Family ID from the ID block.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub family_id: [u8; 16],
+
+    /// Image ID from the ID block.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub image_id: [u8; 16],
+
+    /// The VMPL requested for the report.
+    pub vmpl: u32,
+
+    /// The algorithm used to sign the report.
+    pub sig_algo: u32,
+
+    /// The current TCB of the platform.
+    pub current_tcb: TcbVersion,
+
+    /// Information about the platform.
+    pub platform_info: u64,
+
+    /// `AUTHOR_KEY_EN`, `MASK_CHIP_KEY` and `SIGNING_KEY` bits.
+    pub key_info: u32,
+
+    /// Guest-provided data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub report_data: [u8; 64],
+
+    /// The launch digest of the guest.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub measurement: [u8; 48],
+
+    /// Data provided by the hypervisor at launch.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub host_data: [u8; 32],
+
+    /// SHA-384 digest of the ID public key that signed the ID block.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub id_key_digest: [u8; 48],
+
+    /// SHA-384 digest of the author public key that certified the ID key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub author_key_digest: [u8; 48],
+
+    /// Report ID of this guest.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub report_id: [u8; 32],
+
+    /// Report ID of this guest's migration agent.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub report_id_ma: [u8; 32],
+
+    /// The TCB the VCEK used to sign this report was derived from.
+    pub reported_tcb: TcbVersion,
+
+    /// Family of the CPU (report version 3 and later).
+    pub cpuid_fam_id: u8,
+
+    /// Model of the CPU (report version 3 and later).
+    pub cpuid_mod_id: u8,
+
+    /// Stepping of the CPU (report version 3 and later).
+    pub cpuid_step: u8,
+
+    /// Identifier unique to the chip, or zeroes if `MASK_CHIP_ID` is set.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub chip_id: [u8; 64],
+
+    /// The committed TCB of the platform.
+    pub committed_tcb: TcbVersion,
+
+    /// The current firmware version.
+    pub current: FirmwareVersion,
+
+    /// The committed firmware version.
+    pub committed: FirmwareVersion,
+
+    /// The current TCB at the time the guest was launched.
+    pub launch_tcb: TcbVersion,
+
+    /// Signature over bytes `0x0..0x2a0` of the report.
+    pub signature: Signature,
+}
+
+impl fmt::Debug for AttestationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AttestationReport")
+            .field("version", &self.version)
+            .field("guest_svn", &self.guest_svn)
+            .field("policy", &self.policy)
+            .field("family_id", &Sensitive(&self.family_id))
+            .field("image_id", &Sensitive(&self.image_id))
+            .field("vmpl", &self.vmpl)
+            .field("sig_algo", &self.sig_algo)
+            .field("current_tcb", &self.current_tcb)
+            .field("platform_info", &self.platform_info)
+            .field("key_info", &self.key_info)
+            .field("report_data", &Sensitive(&self.report_data))
+            .field("measurement", &Sensitive(&self.measurement))
+            .field("host_data", &Sensitive(&self.host_data))
+            .field("id_key_digest", &Sensitive(&self.id_key_digest))
+            .field("author_key_digest", &Sensitive(&self.author_key_digest))
+            .field("report_id", &Sensitive(&self.report_id))
+            .field("report_id_ma", &Sensitive(&self.report_id_ma))
+            .field("reported_tcb", &self.reported_tcb)
+            .field("cpuid_fam_id", &self.cpuid_fam_id)
+            .field("cpuid_mod_id", &self.cpuid_mod_id)
+            .field("cpuid_step", &self.cpuid_step)
+            .field("chip_id", &Sensitive(&self.chip_id))
+            .field("committed_tcb", &self.committed_tcb)
+            .field("current", &self.current)
+            .field("committed", &self.committed)
+            .field("launch_tcb", &self.launch_tcb)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl fmt::Display for AttestationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !f.alternate() {
+            return write!(
+                f,
+                "report version {}, VMPL {}, signing key {:?}",
+                self.version,
+                self.vmpl,
+                self.signing_key()
+            );
+        }
+
+        macro_rules! fields {
+            ($($offset:literal $name:literal => $value:expr),* $(,)?) => {
+                f.write_str("ATTESTATION_REPORT Structure")?;
+                $(write!(f, "\n  {:#05x} {:<18} {}", $offset, $name, $value)?;)*
+            };
+        }
+
+        fields! {
+            0x000 "VERSION" => self.version,
+            0x004 "GUEST_SVN" => self.guest_svn,
+            0x008 "POLICY" => format_args!("{:#018x}", self.policy),
+            0x010 "FAMILY_ID" => format_args!("{:?}", Sensitive(&self.family_id)),
+            0x020 "IMAGE_ID" => format_args!("{:?}", Sensitive(&self.image_id)),
+            0x030 "VMPL" => self.vmpl,
+            0x034 "SIGNATURE_ALGO" => self.sig_algo,
+            0x038 "CURRENT_TCB" => self.current_tcb,
+            0x040 "PLATFORM_INFO" => format_args!(
+                "{:#018x} ({:?})",
+                self.platform_info,
+                self.platform_flags()
+            ),
+            0x048 "AUTHOR_KEY_EN" => self.author_key_en(),
+            0x048 "MASK_CHIP_KEY" => self.mask_chip_key(),
+            0x048 "SIGNING_KEY" => format_args!("{:?}", self.signing_key()),
+            0x050 "REPORT_DATA" => format_args!("{:?}", Sensitive(&self.report_data)),
+            0x090 "MEASUREMENT" => format_args!("{:?}", Sensitive(&self.measurement)),
+            0x0c0 "HOST_DATA" => format_args!("{:?}", Sensitive(&self.host_data)),
+            0x0e0 "ID_KEY_DIGEST" => format_args!("{:?}", Sensitive(&self.id_key_digest)),
+            0x110 "AUTHOR_KEY_DIGEST" => format_args!("{:?}", Sensitive(&self.author_key_digest)),
+            0x140 "REPORT_ID" => format_args!("{:?}", Sensitive(&self.report_id)),
+            0x160 "REPORT_ID_MA" => format_args!("{:?}", Sensitive(&self.report_id_ma)),
+            0x180 "REPORTED_TCB" => self.reported_tcb,
+            0x188 "CPUID_FAM_ID" => format_args!("{:#04x}", self.cpuid_fam_id),
+            0x189 "CPUID_MOD_ID" => format_args!("{:#04x}", self.cpuid_mod_id),
+            0x18a "CPUID_STEP" => format_args!("{:#04x}", self.cpuid_step),
+            0x1a0 "CHIP_ID" => format_args!("{:?}", Sensitive(&self.chip_id)),
+            0x1e0 "COMMITTED_TCB" => self.committed_tcb,
+            0x1e8 "CURRENT" => self.current,
+            0x1ec "COMMITTED" => self.committed,
+            0x1f0 "LAUNCH_TCB" => self.launch_tcb,
+            0x2a0 "SIGNATURE" => format_args!("{:?}", self.signature),
+        }
+
+        Ok(())
+    }
+}
+
+impl AttestationReport {
+    /// Size of an encoded attestation report.
+    pub const SIZE: usize = 0x4a0;
+
+    /// Number of leading bytes covered by the signature.
+    pub const SIGNED_SIZE: usize = 0x2a0;
+
+    /// Report versions this crate can decode.
+    pub const VERSIONS: std::ops::RangeInclusive<u32> = 2..=3;
+
+    /// Whether the digest of the author key is included in the report.
+    pub fn author_key_en(&self) -> bool {
+        self.key_info & 1 != 0
+    }
+
+    /// Whether `MASK_CHIP_KEY` is set, in which case the firmware did not
+    /// sign the report with the chip key and the signature is zero.
+    ///
+    /// This does not affect `CHIP_ID`, which is masked by the platform's
+    /// separate `MASK_CHIP_ID` setting.
+    pub fn mask_chip_key(&self) -> bool {
+        self.key_info & (1 << 1) != 0
+    }
+
+    /// The known flags of `PLATFORM_INFO`. Reserved bits are dropped.
+    pub fn platform_flags(&self) -> PlatformInfo {
+        PlatformInfo::from_bits_truncate(self.platform_info)
+    }
+
+    /// The key that signed the report, decoded from `SIGNING_KEY`.
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey::from(((self.key_info >> 2) & 0b111) as u8)
+    }
+
+    /// The chip ID, unless it was masked by the firmware.
+    ///
+    /// A platform with `MASK_CHIP_ID` set reports zeroes, which the report
+    /// does not flag otherwise.
+    pub fn chip_id(&self) -> Option<&[u8; 64]> {
+        if self.chip_id.iter().all(|b| *b == 0) {
+            return None;
+        }
+
+        Some(&self.chip_id)
+    }
+
+    /// A keyed HMAC-SHA384 of the chip ID, unless it was masked.
+    ///
+    /// The result identifies the same chip across reports for whoever
+    /// holds `key`, so it can be logged or used to correlate a fleet
+    /// without storing the raw hardware identifier.
+    pub fn chip_id_hmac(&self, key: &[u8]) -> Option<[u8; 48]> {
+        use hmac::{Hmac, Mac};
+
+        let chip_id = self.chip_id()?;
+        let mut mac =
+            Hmac::<sha2::Sha384>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(chip_id);
+
+        let mut out = [0u8; 48];
+        out.copy_from_slice(&mac.finalize().into_bytes());
+        Some(out)
+    }
+
+    /// Decodes an attestation report.
+    ///
+    /// The input must be exactly [`AttestationReport::SIZE`] bytes long.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, false)
+    }
+
+    /// Decodes an attestation report, rejecting non-zero reserved fields.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, true)
+    }
+
+    fn read(bytes: &[u8], strict: bool) -> Result<Self, Error> {
+        let mut reader = Reader::with_strict(bytes, strict);
+
+        let version = reader.u32()?;
+        if !Self::VERSIONS.contains(&version) {
+            return Err(Error::new(0, ErrorKind::UnsupportedVersion(version)));
+        }
+
+        let guest_svn = reader.u32()?;
+        let policy = reader.u64()?;
+        let family_id = reader.array()?;
+        let image_id = reader.array()?;
+        let vmpl = reader.u32()?;
+        let sig_algo = reader.u32()?;
+        let current_tcb = reader.u64()?.into();
+        let platform_info = reader.u64()?;
+        let key_info = reader.u32()?;
+        reader.reserved(4)?;
+        let report_data = reader.array()?;
+        let measurement = reader.array()?;
+        let host_data = reader.array()?;
+        let id_key_digest = reader.array()?;
+        let author_key_digest = reader.array()?;
+        let report_id = reader.array()?;
+        let report_id_ma = reader.array()?;
+        let reported_tcb = reader.u64()?.into();
+
+        let (cpuid_fam_id, cpuid_mod_id, cpuid_step) = if version >= 3 {
+            (reader.u8()?, reader.u8()?, reader.u8()?)
+        } else {
+            reader.reserved(3)?;
+            (0, 0, 0)
+        };
+        reader.reserved(0x15)?;
+
+        let chip_id = reader.array()?;
+        let committed_tcb = reader.u64()?.into();
+        let current = FirmwareVersion::read(&mut reader)?;
+        let committed = FirmwareVersion::read(&mut reader)?;
+        let launch_tcb = reader.u64()?.into();
+        reader.reserved(Self::SIGNED_SIZE - reader.offset())?;
+
+        let signature = Signature::read(&mut reader)?;
+        reader.finish()?;
+
+        Ok(Self {
+            version,
+            guest_svn,
+            policy,
+            family_id,
+            image_id,
+            vmpl,
+            sig_algo,
+            current_tcb,
+            platform_info,
+            key_info,
+            report_data,
+            measurement,
+            host_data,
+            id_key_digest,
+            author_key_digest,
+            report_id,
+            report_id_ma,
+            reported_tcb,
+            cpuid_fam_id,
+            cpuid_mod_id,
+            cpuid_step,
+            chip_id,
+            committed_tcb,
+            current,
+            committed,
+            launch_tcb,
+            signature,
+        })
+    }
+}
+
+/// A field that differs between two reports.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// The name of the field, as in [`AttestationReport`].
+    pub field: &'static str,
+
+    /// The value in `self`, rendered as text.
+    pub left: String,
+
+    /// The value in `other`, rendered as text.
+    pub right: String,
+}
+
+trait Render {
+    fn render(&self) -> String;
+}
+
+macro_rules! render {
+    ($($ty:ty => $fmt:literal),* $(,)?) => {$(
+        impl Render for $ty {
+            fn render(&self) -> String {
+                format!($fmt, self)
+            }
+        }
+    )*};
+}
+
+render! {
+    u8 => "{}",
+    u32 => "{}",
+    u64 => "{:#018x}",
+    TcbVersion => "{}",
+    FirmwareVersion => "{}",
+}
+
+impl<const N: usize> Render for [u8; N] {
+    fn render(&self) -> String {
+        crate::hex::encode(self)
+    }
+}
+
+impl AttestationReport {
+    /// Compares two reports field by field.
+    ///
+    /// Returns the fields that differ, in report order. Byte fields are
+    /// rendered as hex, so the result can be serialized for tooling.
+    pub fn diff(&self, other: &Self) -> Vec<FieldDiff> {
+        let mut diffs = Vec::new();
+
+        macro_rules! compare {
+            ($($name:literal => $($field:ident).+),* $(,)?) => {$(
+                if self.$($field).+ != other.$($field).+ {
+                    diffs.push(FieldDiff {
+                        field: $name,
+                        left: self.$($field).+.render(),
+                        right: other.$($field).+.render(),
+                    });
+                }
+            )*};
+        }
+
+        compare! {
+            "version" => version,
+            "guest_svn" => guest_svn,
+            "policy" => policy,
+            "family_id" => family_id,
+            "image_id" => image_id,
+            "vmpl" => vmpl,
+            "sig_algo" => sig_algo,
+            "current_tcb" => current_tcb,
+            "platform_info" => platform_info,
+            "key_info" => key_info,
+            "report_data" => report_data,
+            "measurement" => measurement,
+            "host_data" => host_data,
+            "id_key_digest" => id_key_digest,
+            "author_key_digest" => author_key_digest,
+            "report_id" => report_id,
+            "report_id_ma" => report_id_ma,
+            "reported_tcb" => reported_tcb,
+            "cpuid_fam_id" => cpuid_fam_id,
+            "cpuid_mod_id" => cpuid_mod_id,
+            "cpuid_step" => cpuid_step,
+            "chip_id" => chip_id,
+            "committed_tcb" => committed_tcb,
+            "current" => current,
+            "committed" => committed,
+            "launch_tcb" => launch_tcb,
+            "signature.r" => signature.r,
+            "signature.s" => signature.s,
+        }
+
+        diffs
+    }
+}
+
+crate::static_assert!(AttestationReport::SIGNED_SIZE + Signature::SIZE == AttestationReport::SIZE);
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a version 3 report with a distinct value in every field.
+    pub(crate) fn example() -> Vec<u8> {
+        let mut bytes = vec![0u8; AttestationReport::SIZE];
+
+        bytes[0x00..0x04].copy_from_slice(&3u32.to_le_bytes());
+        bytes[0x04..0x08].copy_from_slice(&7u32.to_le_bytes());
+        bytes[0x08..0x10].copy_from_slice(&0x3_0000u64.to_le_bytes());
+        bytes[0x10..0x20].copy_from_slice(&[0x11; 16]);
+        bytes[0x20..0x30].copy_from_slice(&[0x22; 16]);
+        bytes[0x30..0x34].copy_from_slice(&1u32.to_le_bytes());
+        bytes[0x34..0x38].copy_from_slice(&1u32.to_le_bytes());
+        bytes[0x38..0x40].copy_from_slice(&[3, 0, 0, 0, 0, 0, 8, 115]);
+        bytes[0x40..0x48].copy_from_slice(&0b11u64.to_le_bytes());
+        bytes[0x48..0x4c].copy_from_slice(&0b101u32.to_le_bytes());
+        bytes[0x50..0x90].copy_from_slice(&[0x33; 64]);
+        bytes[0x90..0xc0].copy_from_slice(&[0x44; 48]);
+        bytes[0xc0..0xe0].copy_from_slice(&[0x55; 32]);
+        bytes[0xe0..0x110].copy_from_slice(&[0x66; 48]);
+        bytes[0x110..0x140].copy_from_slice(&[0x77; 48]);
+        bytes[0x140..0x160].copy_from_slice(&[0x88; 32]);
+        bytes[0x160..0x180].copy_from_slice(&[0x99; 32]);
+        bytes[0x180..0x188].copy_from_slice(&[2, 0, 0, 0, 0, 0, 6, 100]);
+        bytes[0x188..0x18b].copy_from_slice(&[0x19, 0x01, 0x01]);
+        bytes[0x1a0..0x1e0].copy_from_slice(&[0xaa; 64]);
+        bytes[0x1e0..0x1e8].copy_from_slice(&[1, 0, 0, 0, 0, 0, 5, 90]);
+        bytes[0x1e8..0x1eb].copy_from_slice(&[13, 55, 1]);
+        bytes[0x1ec..0x1ef].copy_from_slice(&[10, 52, 1]);
+        bytes[0x1f0..0x1f8].copy_from_slice(&[3, 0, 0, 0, 0, 0, 8, 115]);
+        bytes[0x2a0..0x2e8].copy_from_slice(&[0xbb; 72]);
+        bytes[0x2e8..0x330].copy_from_slice(&[0xcc; 72]);
+
+        bytes
+    }
+
+    #[test]
+    fn decode() {
+        let report = AttestationReport::from_bytes(&example()).unwrap();
+
+        assert_eq!(report.version, 3);
+        assert_eq!(report.guest_svn, 7);
+        assert_eq!(report.policy, 0x3_0000);
+        assert_eq!(report.family_id, [0x11; 16]);
+        assert_eq!(report.image_id, [0x22; 16]);
+        assert_eq!(report.vmpl, 1);
+        assert_eq!(report.sig_algo, 1);
+        assert_eq!(
+            report.current_tcb,
+            TcbVersion {
+                bootloader: 3,
+                tee: 0,
+                snp: 8,
+                microcode: 115,
+            }
+        );
+        assert_eq!(report.platform_info, 0b11);
+        assert!(report.author_key_en());
+        assert!(!report.mask_chip_key());
+        assert_eq!(report.signing_key(), SigningKey::Vlek);
+        assert_eq!(report.signing_key().cert_guid(), Some(Guid::VLEK));
+        assert_eq!(report.report_data, [0x33; 64]);
+        assert_eq!(report.measurement, [0x44; 48]);
+        assert_eq!(report.host_data, [0x55; 32]);
+        assert_eq!(report.id_key_digest, [0x66; 48]);
+        assert_eq!(report.author_key_digest, [0x77; 48]);
+        assert_eq!(report.report_id, [0x88; 32]);
+        assert_eq!(report.report_id_ma, [0x99; 32]);
+        assert_eq!(report.reported_tcb.snp, 6);
+        assert_eq!(
+            (report.cpuid_fam_id, report.cpuid_mod_id, report.cpuid_step),
+            (0x19, 0x01, 0x01)
+        );
+        assert_eq!(report.chip_id, [0xaa; 64]);
+        assert_eq!(report.committed_tcb.microcode, 90);
+        assert_eq!(
+            report.current,
+            FirmwareVersion {
+                major: 1,
+                minor: 55,
+                build: 13,
+            }
+        );
+        assert_eq!(report.committed.minor, 52);
+        assert_eq!(report.launch_tcb, report.current_tcb);
+        assert_eq!(report.signature.r, [0xbb; 72]);
+        assert_eq!(report.signature.s, [0xcc; 72]);
+    }
+
+    #[test]
+    fn diff() {
+        let left = AttestationReport::from_bytes(&example()).unwrap();
+        assert!(left.diff(&left).is_empty());
+
+        let mut right = left;
+        right.guest_svn = 8;
+        right.policy |= 1 << 19;
+        right.measurement[47] = 0;
+        right.current.minor = 56;
+
+        assert_eq!(
+            left.diff(&right),
+            vec![
+                FieldDiff {
+                    field: "guest_svn",
+                    left: "7".into(),
+                    right: "8".into(),
+                },
+                FieldDiff {
+                    field: "policy",
+                    left: "0x0000000000030000".into(),
+                    right: "0x00000000000b0000".into(),
+                },
+                FieldDiff {
+                    field: "measurement",
+                    left: "44".repeat(48),
+                    right: format!("{}00", "44".repeat(47)),
+                },
+                FieldDiff {
+                    field: "current",
+                    left: "1.55.13".into(),
+                    right: "1.56.13".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let mut bytes = example();
+        assert!(AttestationReport::from_bytes_strict(&bytes).is_ok());
+
+        for &offset in [0x4c, 0x18b, 0x1eb, 0x200, 0x400].iter() {
+            bytes[offset] = 1;
+            assert!(AttestationReport::from_bytes(&bytes).is_ok());
+
+            let err = AttestationReport::from_bytes_strict(&bytes).unwrap_err();
+            assert_eq!(err.offset(), offset);
+            assert_eq!(err.kind(), ErrorKind::NonZeroReserved);
+            bytes[offset] = 0;
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let report = AttestationReport::from_bytes(&example()).unwrap();
+        let json = serde_json::to_value(report).unwrap();
+
+        assert_eq!(json["measurement"], "44".repeat(48));
+        assert_eq!(json["chip_id"], "aa".repeat(64));
+        assert_eq!(json["current"]["minor"], 55);
+        assert_eq!(
+            serde_json::from_value::<AttestationReport>(json).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn signing_key() {
+        assert_eq!(SigningKey::from(0), SigningKey::Vcek);
+        assert_eq!(SigningKey::from(7), SigningKey::None);
+        assert_eq!(SigningKey::from(3), SigningKey::Reserved(3));
+        assert_eq!(SigningKey::None.cert_guid(), None);
+    }
+
+    #[test]
+    fn platform_flags() {
+        let mut report = AttestationReport::from_bytes(&example()).unwrap();
+        assert_eq!(
+            report.platform_flags(),
+            PlatformInfo::SMT_EN | PlatformInfo::TSME_EN
+        );
+
+        report.platform_info = 1 << 63 | 1 << 4;
+        assert_eq!(report.platform_flags(), PlatformInfo::CIPHERTEXT_HIDING_EN);
+    }
+
+    #[test]
+    fn display() {
+        let report = AttestationReport::from_bytes(&example()).unwrap();
+        assert_eq!(
+            report.to_string(),
+            "report version 3, VMPL 1, signing key Vlek"
+        );
+
+        let explained = format!("{:#}", report);
+        let lines: Vec<_> = explained.lines().collect();
+        assert_eq!(lines[0], "ATTESTATION_REPORT Structure");
+        assert_eq!(lines[1], "  0x000 VERSION            3");
+        assert!(lines.contains(&"  0x040 PLATFORM_INFO      0x0000000000000003 (SMT_EN | TSME_EN)"));
+        assert!(lines.contains(&"  0x048 SIGNING_KEY        Vlek"));
+        assert!(lines.contains(&"  0x1e8 CURRENT            1.55.13"));
+        assert_eq!(lines.len(), 30);
+
+        assert_eq!(
+            format!("{:#}", report.reported_tcb),
+            "bootloader 2 [byte 0], tee 0 [byte 1], snp 6 [byte 6], microcode 100 [byte 7] \
+             (TCB_VERSION Structure)"
+        );
+    }
+
+    #[test]
+    fn chip_id() {
+        let mut report = AttestationReport::from_bytes(&example()).unwrap();
+        assert_eq!(report.chip_id(), Some(&[0xaa; 64]));
+
+        let a = report.chip_id_hmac(b"key a").unwrap();
+        assert_eq!(report.chip_id_hmac(b"key a"), Some(a));
+        assert_ne!(report.chip_id_hmac(b"key b"), Some(a));
+
+        // MASK_CHIP_KEY masks the signature, not the chip ID.
+        report.key_info |= 1 << 1;
+        assert_eq!(report.chip_id(), Some(&[0xaa; 64]));
+        assert_eq!(report.chip_id_hmac(b"key a"), Some(a));
+
+        report.chip_id = [0; 64];
+        assert_eq!(report.chip_id(), None);
+        assert_eq!(report.chip_id_hmac(b"key a"), None);
+    }
+
+    #[test]
+    fn tcb_roundtrip() {
+        let raw = u64::from_le_bytes([3, 0, 0, 0, 0, 0, 8, 115]);
+        assert_eq!(u64::from(TcbVersion::from(raw)), raw);
+
+        // The field's numeric value, independent of the host's byte order.
+        assert_eq!(raw, 0x7308_0000_0000_0003);
+        assert_eq!(TcbVersion::from(raw).microcode, 0x73);
+    }
+
+    #[test]
+    fn version() {
+        let mut bytes = example();
+        bytes[0] = 1;
+
+        let err = AttestationReport::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0);
+        assert_eq!(err.kind(), ErrorKind::UnsupportedVersion(1));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = example();
+
+        for len in 0..bytes.len() {
+            assert!(AttestationReport::from_bytes(&bytes[..len]).is_err());
+        }
+
+        let err = AttestationReport::from_bytes(&bytes[..0x300]).unwrap_err();
+        assert_eq!(err.offset(), 0x2e8);
+        assert_eq!(err.kind(), ErrorKind::Truncated { needed: 72 });
+    }
+
+    #[test]
+    fn trailing() {
+        let mut bytes = example();
+        bytes.push(0);
+
+        let err = AttestationReport::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.offset(), AttestationReport::SIZE);
+        assert_eq!(err.kind(), ErrorKind::TrailingBytes);
+    }
+}
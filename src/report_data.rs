@@ -0,0 +1,442 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Versioned layouts for the 64-byte `REPORT_DATA` field.
+//!
+//! The firmware copies `REPORT_DATA` into the attestation report verbatim
+//! and assigns it no meaning. A [`Schema`] gives those bytes a structure:
+//! an 8-byte header made up of a 4-byte magic, a little-endian version
+//! and two reserved bytes, followed by named fixed-size fields and zero
+//! padding. A [`Registry`] decodes data produced by any known schema.
+//!
+//! ```
+//! use snp::report_data::{Field, Registry, Schema};
+//!
+//! static BINDING: Schema = Schema {
+//!     magic: *b"EXMP",
+//!     version: 1,
+//!     fields: &[
+//!         Field { name: "nonce", len: 24 },
+//!         Field { name: "key_hash", len: 32 },
+//!     ],
+//! };
+//!
+//! let data = BINDING
+//!     .encode(&[("nonce", &[1; 24]), ("key_hash", &[2; 32])])
+//!     .unwrap();
+//!
+//! let mut registry = Registry::default();
+//! registry.register(&BINDING).unwrap();
+//!
+//! let decoded = registry.decode(&data).unwrap();
+//! assert_eq!(decoded.get("key_hash"), Some(&[2; 32][..]));
+//! ```
+
+use std::fmt;
+
+/// Size of the `REPORT_DATA` field.
+pub const SIZE: usize = 64;
+
+/// Errors produced when encoding or decoding `REPORT_DATA`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The schema's fields do not fit after the header.
+    SchemaTooLarge,
+
+    /// A schema with the same magic and version is already registered.
+    DuplicateSchema,
+
+    /// No value was supplied for a field of the schema.
+    MissingField(&'static str),
+
+    /// A value was supplied for a field the schema does not define.
+    UnknownField(String),
+
+    /// A value does not have the size of its field.
+    FieldLength {
+        /// The field the value was supplied for.
+        name: &'static str,
+        /// The size of the field.
+        expected: usize,
+        /// The size of the value.
+        actual: usize,
+    },
+
+    /// The data does not start with the schema's magic.
+    BadMagic,
+
+    /// The data was produced by an unknown version of the schema.
+    UnsupportedVersion(u16),
+
+    /// The reserved header bytes or the padding are not zero.
+    NonZeroPadding,
+
+    /// The schema defines two fields with this name.
+    DuplicateField(&'static str),
+
+    /// More than one value was supplied for this field.
+    DuplicateValue(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::SchemaTooLarge => write!(f, "schema fields exceed {} bytes", SIZE),
+            Error::DuplicateSchema => write!(f, "schema already registered"),
+            Error::MissingField(name) => write!(f, "missing field {}", name),
+            Error::UnknownField(name) => write!(f, "unknown field {}", name),
+            Error::FieldLength {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "field {} must be {} bytes, got {}",
+                name, expected, actual
+            ),
+            Error::BadMagic => write!(f, "unknown magic"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported schema version {}", v),
+            Error::NonZeroPadding => write!(f, "reserved bytes are not zero"),
+            Error::DuplicateField(name) => write!(f, "field {} defined more than once", name),
+            Error::DuplicateValue(name) => write!(f, "field {} supplied more than once", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// A stable numeric code for the error, in the range 400-499.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::SchemaTooLarge => 400,
+            Error::DuplicateSchema => 401,
+            Error::MissingField(_) => 402,
+            Error::UnknownField(_) => 403,
+            Error::FieldLength { .. } => 404,
+            Error::BadMagic => 405,
+            Error::UnsupportedVersion(_) => 406,
+            Error::NonZeroPadding => 407,
+            Error::DuplicateField(_) => 408,
+            Error::DuplicateValue(_) => 409,
+        }
+    }
+}
+
+/// A named, fixed-size field of a [`Schema`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// The name of the field.
+    pub name: &'static str,
+
+    /// The size of the field in bytes.
+    pub len: usize,
+}
+
+/// A versioned layout of `REPORT_DATA`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Schema {
+    /// Identifies the schema family.
+    pub magic: [u8; 4],
+
+    /// The version of the layout within its family.
+    pub version: u16,
+
+    /// The fields following the header, in order.
+    pub fields: &'static [Field],
+}
+
+impl Schema {
+    /// Size of the header preceding the fields.
+    pub const HEADER_SIZE: usize = 8;
+
+    /// Checks that the fields fit into `REPORT_DATA` and that their names
+    /// are unique.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (i, field) in self.fields.iter().enumerate() {
+            if self.fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(Error::DuplicateField(field.name));
+            }
+        }
+
+        let size = self
+            .fields
+            .iter()
+            .try_fold(Self::HEADER_SIZE, |size, f| size.checked_add(f.len));
+
+        match size {
+            Some(size) if size <= SIZE => Ok(()),
+            _ => Err(Error::SchemaTooLarge),
+        }
+    }
+
+    /// Encodes the given field values.
+    ///
+    /// Every field of the schema must be supplied exactly once.
+    pub fn encode(&self, values: &[(&str, &[u8])]) -> Result<[u8; SIZE], Error> {
+        self.validate()?;
+
+        if let Some((name, _)) = values
+            .iter()
+            .find(|(name, _)| !self.fields.iter().any(|f| f.name == *name))
+        {
+            return Err(Error::UnknownField((*name).to_string()));
+        }
+
+        for (i, (name, _)) in values.iter().enumerate() {
+            if values[..i].iter().any(|(n, _)| n == name) {
+                return Err(Error::DuplicateValue((*name).to_string()));
+            }
+        }
+
+        let mut data = [0u8; SIZE];
+        data[..4].copy_from_slice(&self.magic);
+        data[4..6].copy_from_slice(&self.version.to_le_bytes());
+
+        let mut offset = Self::HEADER_SIZE;
+        for field in self.fields {
+            let value = values
+                .iter()
+                .find(|(name, _)| *name == field.name)
+                .map(|(_, value)| *value)
+                .ok_or(Error::MissingField(field.name))?;
+
+            if value.len() != field.len {
+                return Err(Error::FieldLength {
+                    name: field.name,
+                    expected: field.len,
+                    actual: value.len(),
+                });
+            }
+
+            data[offset..offset + field.len].copy_from_slice(value);
+            offset += field.len;
+        }
+
+        Ok(data)
+    }
+
+    /// Decodes data produced by this schema.
+    pub fn decode<'a>(&'static self, data: &'a [u8; SIZE]) -> Result<Decoded<'a>, Error> {
+        self.validate()?;
+
+        if data[..4] != self.magic {
+            return Err(Error::BadMagic);
+        }
+
+        let version = u16::from_le_bytes([data[4], data[5]]);
+        if version != self.version {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let end = Self::HEADER_SIZE + self.fields.iter().map(|f| f.len).sum::<usize>();
+        if data[6..8].iter().chain(&data[end..]).any(|b| *b != 0) {
+            return Err(Error::NonZeroPadding);
+        }
+
+        Ok(Decoded { schema: self, data })
+    }
+}
+
+/// `REPORT_DATA` decoded according to a [`Schema`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Decoded<'a> {
+    schema: &'static Schema,
+    data: &'a [u8; SIZE],
+}
+
+impl<'a> Decoded<'a> {
+    /// The schema the data was decoded with.
+    pub fn schema(&self) -> &'static Schema {
+        self.schema
+    }
+
+    /// Returns the value of the named field.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        let mut offset = Schema::HEADER_SIZE;
+
+        for field in self.schema.fields {
+            if field.name == name {
+                return Some(&self.data[offset..offset + field.len]);
+            }
+
+            offset += field.len;
+        }
+
+        None
+    }
+}
+
+/// A set of schemas, looked up by magic and version.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    schemas: Vec<&'static Schema>,
+}
+
+impl Registry {
+    /// Adds a schema to the registry.
+    pub fn register(&mut self, schema: &'static Schema) -> Result<(), Error> {
+        schema.validate()?;
+
+        if self
+            .schemas
+            .iter()
+            .any(|s| s.magic == schema.magic && s.version == schema.version)
+        {
+            return Err(Error::DuplicateSchema);
+        }
+
+        self.schemas.push(schema);
+        Ok(())
+    }
+
+    /// Decodes data produced by any registered schema.
+    pub fn decode<'a>(&self, data: &'a [u8; SIZE]) -> Result<Decoded<'a>, Error> {
+        let mut family = self.schemas.iter().filter(|s| data[..4] == s.magic);
+        let version = u16::from_le_bytes([data[4], data[5]]);
+
+        match family.clone().find(|s| s.version == version) {
+            Some(schema) => schema.decode(data),
+            None if family.next().is_some() => Err(Error::UnsupportedVersion(version)),
+            None => Err(Error::BadMagic),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static V1: Schema = Schema {
+        magic: *b"TEST",
+        version: 1,
+        fields: &[
+            Field {
+                name: "nonce",
+                len: 16,
+            },
+            Field {
+                name: "key_hash",
+                len: 32,
+            },
+        ],
+    };
+
+    static V2: Schema = Schema {
+        magic: *b"TEST",
+        version: 2,
+        fields: &[Field {
+            name: "claims_hash",
+            len: 48,
+        }],
+    };
+
+    static HUGE: Schema = Schema {
+        magic: *b"HUGE",
+        version: 1,
+        fields: &[Field {
+            name: "blob",
+            len: 57,
+        }],
+    };
+
+    #[test]
+    fn encode() {
+        let data = V1
+            .encode(&[("key_hash", &[2; 32]), ("nonce", &[1; 16])])
+            .unwrap();
+
+        assert_eq!(&data[..8], b"TEST\x01\x00\x00\x00");
+        assert_eq!(&data[8..24], &[1; 16]);
+        assert_eq!(&data[24..56], &[2; 32]);
+        assert_eq!(&data[56..], &[0; 8]);
+    }
+
+    #[test]
+    fn encode_errors() {
+        assert_eq!(
+            V1.encode(&[("nonce", &[1; 16])]),
+            Err(Error::MissingField("key_hash"))
+        );
+        assert_eq!(
+            V1.encode(&[("nonce", &[1; 15]), ("key_hash", &[2; 32])]),
+            Err(Error::FieldLength {
+                name: "nonce",
+                expected: 16,
+                actual: 15
+            })
+        );
+        assert_eq!(
+            V2.encode(&[("claims_hash", &[0; 48]), ("extra", &[])]),
+            Err(Error::UnknownField("extra".into()))
+        );
+        assert_eq!(HUGE.encode(&[]), Err(Error::SchemaTooLarge));
+        assert_eq!(
+            V1.encode(&[
+                ("nonce", &[1; 16]),
+                ("key_hash", &[2; 32]),
+                ("nonce", &[3; 16])
+            ]),
+            Err(Error::DuplicateValue("nonce".into()))
+        );
+    }
+
+    #[test]
+    fn duplicate_field() {
+        static TWICE: Schema = Schema {
+            magic: *b"TWCE",
+            version: 1,
+            fields: &[
+                Field {
+                    name: "nonce",
+                    len: 16,
+                },
+                Field {
+                    name: "nonce",
+                    len: 16,
+                },
+            ],
+        };
+
+        assert_eq!(TWICE.validate(), Err(Error::DuplicateField("nonce")));
+        assert_eq!(
+            TWICE.encode(&[("nonce", &[1; 16])]),
+            Err(Error::DuplicateField("nonce"))
+        );
+        assert_eq!(
+            Registry::default().register(&TWICE),
+            Err(Error::DuplicateField("nonce"))
+        );
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = Registry::default();
+        registry.register(&V1).unwrap();
+        registry.register(&V2).unwrap();
+        assert_eq!(registry.register(&V1), Err(Error::DuplicateSchema));
+        assert_eq!(registry.register(&HUGE), Err(Error::SchemaTooLarge));
+
+        let data = V2.encode(&[("claims_hash", &[3; 48])]).unwrap();
+        let decoded = registry.decode(&data).unwrap();
+        assert_eq!(decoded.schema(), &V2);
+        assert_eq!(decoded.get("claims_hash"), Some(&[3; 48][..]));
+        assert_eq!(decoded.get("nonce"), None);
+
+        let mut data = data;
+        data[4] = 3;
+        assert_eq!(registry.decode(&data), Err(Error::UnsupportedVersion(3)));
+
+        data[0] = b'X';
+        assert_eq!(registry.decode(&data), Err(Error::BadMagic));
+    }
+
+    #[test]
+    fn padding() {
+        let mut data = V1
+            .encode(&[("nonce", &[1; 16]), ("key_hash", &[2; 32])])
+            .unwrap();
+        data[63] = 1;
+
+        assert_eq!(V1.decode(&data), Err(Error::NonZeroPadding));
+    }
+}
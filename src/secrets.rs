@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The secrets page installed by the firmware at launch.
+
+use crate::parse::{Error, ErrorKind, Reader};
+
+use std::fmt;
+
+/// The contents of the secrets page.
+///
+/// See "Secrets Page Format" in the SEV-SNP firmware ABI specification.
+/// The VMPCKs are secret, so they are omitted from the `Debug` output and,
+/// with the `serde` feature, from serialization.
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct SecretsPage {
+    /// Version of the secrets page format.
+    pub version: u32,
+
+    /// Whether the guest was launched with an incoming migration image.
+    pub imi_en: bool,
+
+    /// Family, model and stepping of the CPU, as reported by CPUID.
+    pub fms: u32,
+
+    /// Guest-OS-visible workarounds provided by the hypervisor at launch.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub gosvw: [u8; 16],
+
+    /// The VM platform communication keys, indexed by VMPL.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub vmpck: [[u8; 32]; 4],
+
+    /// Area reserved for use by the guest OS.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub guest_area_0: [u8; 96],
+
+    /// Bitmap of the VMSA tweak applied by the firmware.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub vmsa_tweak_bitmap: [u8; 64],
+
+    /// Second area reserved for use by the guest OS.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub guest_area_1: [u8; 32],
+
+    /// TSC scaling factor (secrets page version 3 and later).
+    pub tsc_factor: u32,
+}
+
+impl fmt::Debug for SecretsPage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SecretsPage")
+            .field("version", &self.version)
+            .field("imi_en", &self.imi_en)
+            .field("fms", &self.fms)
+            .field("gosvw", &self.gosvw)
+            .field("vmpck", &"<redacted>")
+            .field("guest_area_0", &&self.guest_area_0[..])
+            .field("vmsa_tweak_bitmap", &&self.vmsa_tweak_bitmap[..])
+            .field("guest_area_1", &self.guest_area_1)
+            .field("tsc_factor", &self.tsc_factor)
+            .finish()
+    }
+}
+
+impl SecretsPage {
+    /// Size of the secrets page.
+    pub const SIZE: usize = 4096;
+
+    /// Secrets page versions this crate can decode.
+    pub const VERSIONS: std::ops::RangeInclusive<u32> = 2..=3;
+
+    /// Decodes the secrets page.
+    ///
+    /// The input must be exactly one page long.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, false)
+    }
+
+    /// Decodes the secrets page, rejecting non-zero reserved fields.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(bytes, true)
+    }
+
+    fn read(bytes: &[u8], strict: bool) -> Result<Self, Error> {
+        let mut reader = Reader::with_strict(bytes, strict);
+
+        let version = reader.u32()?;
+        if !Self::VERSIONS.contains(&version) {
+            return Err(Error::new(0, ErrorKind::UnsupportedVersion(version)));
+        }
+
+        let imi_en = reader.u32()? & 1 != 0;
+        let fms = reader.u32()?;
+        reader.reserved(4)?;
+        let gosvw = reader.array()?;
+        let vmpck = [
+            reader.array()?,
+            reader.array()?,
+            reader.array()?,
+            reader.array()?,
+        ];
+        let guest_area_0 = reader.array()?;
+        let vmsa_tweak_bitmap = reader.array()?;
+        let guest_area_1 = reader.array()?;
+        let tsc_factor = if version >= 3 {
+            reader.u32()?
+        } else {
+            reader.reserved(4)?;
+            0
+        };
+        reader.reserved(Self::SIZE - reader.offset())?;
+        reader.finish()?;
+
+        Ok(Self {
+            version,
+            imi_en,
+            fms,
+            gosvw,
+            vmpck,
+            guest_area_0,
+            vmsa_tweak_bitmap,
+            guest_area_1,
+            tsc_factor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Vec<u8> {
+        let mut bytes = vec![0u8; SecretsPage::SIZE];
+
+        bytes[0x00..0x04].copy_from_slice(&3u32.to_le_bytes());
+        bytes[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+        bytes[0x08..0x0c].copy_from_slice(&0x00a0_0f11u32.to_le_bytes());
+        bytes[0x10..0x20].copy_from_slice(&[0x01; 16]);
+        bytes[0x20..0x40].copy_from_slice(&[0xa0; 32]);
+        bytes[0x40..0x60].copy_from_slice(&[0xa1; 32]);
+        bytes[0x60..0x80].copy_from_slice(&[0xa2; 32]);
+        bytes[0x80..0xa0].copy_from_slice(&[0xa3; 32]);
+        bytes[0xa0..0x100].copy_from_slice(&[0x02; 96]);
+        bytes[0x100..0x140].copy_from_slice(&[0x03; 64]);
+        bytes[0x140..0x160].copy_from_slice(&[0x04; 32]);
+        bytes[0x160..0x164].copy_from_slice(&42u32.to_le_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn decode() {
+        let page = SecretsPage::from_bytes(&example()).unwrap();
+
+        assert_eq!(page.version, 3);
+        assert!(page.imi_en);
+        assert_eq!(page.fms, 0x00a0_0f11);
+        assert_eq!(page.gosvw, [0x01; 16]);
+        assert_eq!(page.vmpck, [[0xa0; 32], [0xa1; 32], [0xa2; 32], [0xa3; 32]]);
+        assert_eq!(page.guest_area_0, [0x02; 96]);
+        assert_eq!(page.vmsa_tweak_bitmap, [0x03; 64]);
+        assert_eq!(page.guest_area_1, [0x04; 32]);
+        assert_eq!(page.tsc_factor, 42);
+    }
+
+    #[test]
+    fn debug_hides_keys() {
+        let page = SecretsPage::from_bytes(&example()).unwrap();
+        let debug = format!("{:?}", page);
+
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("160"));
+    }
+
+    #[test]
+    fn strict() {
+        let mut bytes = example();
+        assert!(SecretsPage::from_bytes_strict(&bytes).is_ok());
+
+        bytes[0x800] = 1;
+        assert!(SecretsPage::from_bytes(&bytes).is_ok());
+        let err = SecretsPage::from_bytes_strict(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 0x800);
+        assert_eq!(err.kind(), ErrorKind::NonZeroReserved);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        let page = SecretsPage::from_bytes(&example()).unwrap();
+        let json = serde_json::to_value(page).unwrap();
+
+        assert_eq!(json["gosvw"], "01".repeat(16));
+        assert!(json.get("vmpck").is_none());
+    }
+
+    #[test]
+    fn malformed() {
+        let bytes = example();
+
+        let err = SecretsPage::from_bytes(&bytes[..0x30]).unwrap_err();
+        assert_eq!(err.offset(), 0x20);
+        assert_eq!(err.kind(), ErrorKind::Truncated { needed: 32 });
+
+        let err = SecretsPage::from_bytes(&bytes[..4095]).unwrap_err();
+        assert_eq!(err.offset(), 0x164);
+
+        let mut bytes = bytes;
+        bytes[0] = 9;
+        let err = SecretsPage::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedVersion(9));
+    }
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hex encoding of fixed-size byte arrays in serde formats.
+//!
+//! Without these helpers serde renders a `[u8; 48]` as an array of 48
+//! integers, or not at all for arrays longer than 32 bytes. Use this
+//! module with `#[serde(with = "snp::serde_hex")]` on a byte array field,
+//! or [`serde_hex::option`](option) and [`serde_hex::vec`](mod@vec) on
+//! optional arrays and vectors of arrays. The [`Bytes`] wrapper does the
+//! same for values that are not struct fields.
+//!
+//! ```
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Expected {
+//!     #[serde(with = "snp::serde_hex")]
+//!     measurement: [u8; 48],
+//! }
+//!
+//! let json = serde_json::to_string(&Expected { measurement: [0xab; 48] }).unwrap();
+//! assert_eq!(json, format!(r#"{{"measurement":"{}"}}"#, "ab".repeat(48)));
+//! ```
+
+use crate::hex;
+
+use serde::de::{Deserializer, Error};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+fn decode<'de, D: Deserializer<'de>, const N: usize>(hex: &str) -> Result<[u8; N], D::Error> {
+    hex::decode(hex).ok_or_else(|| D::Error::custom(format!("expected {} hex-encoded bytes", N)))
+}
+
+/// Serializes a byte array as a hex string.
+pub fn serialize<S: Serializer, const N: usize>(
+    bytes: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+/// Deserializes a byte array from a hex string of exactly `2 * N` digits.
+pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    decode::<D, N>(&String::deserialize(deserializer)?)
+}
+
+/// Optional byte arrays as hex strings or `null`.
+pub mod option {
+    use super::*;
+
+    /// Serializes an optional byte array.
+    pub fn serialize<S: Serializer, const N: usize>(
+        item: &Option<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match item {
+            Some(bytes) => serializer.serialize_some(&hex::encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional byte array.
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Option<[u8; N]>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| decode::<D, N>(&hex))
+            .transpose()
+    }
+}
+
+/// Vectors of byte arrays as sequences of hex strings.
+pub mod vec {
+    use super::*;
+    use serde::ser::SerializeSeq;
+
+    /// Serializes a sequence of byte arrays.
+    pub fn serialize<S: Serializer, const N: usize>(
+        items: &[[u8; N]],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(items.len()))?;
+        for item in items {
+            seq.serialize_element(&hex::encode(item))?;
+        }
+        seq.end()
+    }
+
+    /// Deserializes a sequence of byte arrays.
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; N]>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| decode::<D, N>(hex))
+            .collect()
+    }
+}
+
+/// A byte array that serializes as a hex string.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Bytes<const N: usize>(pub [u8; N]);
+
+/// A 32-byte value, such as `HOST_DATA` or a report ID.
+pub type Bytes32 = Bytes<32>;
+
+/// A 48-byte value, such as a measurement or a SHA-384 digest.
+pub type Bytes48 = Bytes<48>;
+
+/// A 64-byte value, such as `REPORT_DATA` or a chip ID.
+pub type Bytes64 = Bytes<64>;
+
+impl<const N: usize> From<[u8; N]> for Bytes<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> From<Bytes<N>> for [u8; N] {
+    fn from(bytes: Bytes<N>) -> Self {
+        bytes.0
+    }
+}
+
+impl<const N: usize> fmt::Display for Bytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&hex::encode(&self.0))
+    }
+}
+
+impl<const N: usize> fmt::Debug for Bytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bytes({})", self)
+    }
+}
+
+impl<const N: usize> Serialize for Bytes<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Bytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        let value = Bytes48::from([0xab; 48]);
+        let json = serde_json::to_string(&value).unwrap();
+
+        assert_eq!(json, format!("\"{}\"", "ab".repeat(48)));
+        assert_eq!(serde_json::from_str::<Bytes48>(&json).unwrap(), value);
+        assert_eq!(value.to_string(), "ab".repeat(48));
+
+        assert!(serde_json::from_str::<Bytes48>("\"abab\"").is_err());
+        assert!(serde_json::from_str::<Bytes64>(&json).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Named constants from the [SEV-SNP firmware ABI specification].
+//!
+//! Each submodule mirrors one table of the specification and provides a
+//! `name()` lookup returning the specification's name for a raw value.
+//!
+//! [SEV-SNP firmware ABI specification]: https://www.amd.com/system/files/TechDocs/56860.pdf
+
+macro_rules! spec_tables {
+    ($(
+        $(#[$meta:meta])*
+        pub mod $module:ident: $ty:ty {
+            $($(#[$cmeta:meta])* $name:ident = $value:expr,)*
+        }
+    )*) => {$(
+        $(#[$meta])*
+        pub mod $module {
+            $($(#[$cmeta])* pub const $name: $ty = $value;)*
+
+            /// Returns the specification name of `value`, if it is defined.
+            pub fn name(value: $ty) -> Option<&'static str> {
+                match value {
+                    $($name => Some(stringify!($name)),)*
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+spec_tables! {
+    /// Firmware command codes ("Command Identifiers" in the specification).
+    pub mod command: u32 {
+        /// Initializes the platform for SNP.
+        SNP_INIT = 0x81,
+        /// Shuts down SNP on the platform.
+        SNP_SHUTDOWN = 0x82,
+        /// Queries the status of the platform.
+        SNP_PLATFORM_STATUS = 0x83,
+        /// Completes a data fabric flush.
+        SNP_DF_FLUSH = 0x84,
+        /// Initializes the platform with extended parameters.
+        SNP_INIT_EX = 0x85,
+        /// Shuts down SNP with extended parameters.
+        SNP_SHUTDOWN_EX = 0x86,
+        /// Destroys a guest context.
+        SNP_DECOMMISSION = 0x90,
+        /// Assigns an ASID to a guest.
+        SNP_ACTIVATE = 0x91,
+        /// Queries the status of a guest.
+        SNP_GUEST_STATUS = 0x92,
+        /// Creates a guest context.
+        SNP_GCTX_CREATE = 0x93,
+        /// Forwards a guest message to the firmware.
+        SNP_GUEST_REQUEST = 0x94,
+        /// Assigns an ASID to a guest, specifying the cores it may run on.
+        SNP_ACTIVATE_EX = 0x95,
+        /// Begins the launch of a guest.
+        SNP_LAUNCH_START = 0xa0,
+        /// Inserts pages into the guest.
+        SNP_LAUNCH_UPDATE = 0xa1,
+        /// Completes the launch of a guest.
+        SNP_LAUNCH_FINISH = 0xa2,
+        /// Decrypts guest memory for debugging.
+        SNP_DBG_DECRYPT = 0xb0,
+        /// Encrypts guest memory for debugging.
+        SNP_DBG_ENCRYPT = 0xb1,
+        /// Swaps a guest page out.
+        SNP_PAGE_SWAP_OUT = 0xc0,
+        /// Swaps a guest page in.
+        SNP_PAGE_SWAP_IN = 0xc1,
+        /// Moves a guest page.
+        SNP_PAGE_MOVE = 0xc2,
+        /// Initializes a metadata page.
+        SNP_PAGE_MD_INIT = 0xc3,
+        /// Sets the state of a page.
+        SNP_PAGE_SET_STATE = 0xc6,
+        /// Reclaims a firmware or guest page.
+        SNP_PAGE_RECLAIM = 0xc7,
+        /// Splits a 2MB page into 4KB pages.
+        SNP_PAGE_UNSMASH = 0xc8,
+        /// Sets the system-wide configuration.
+        SNP_CONFIG = 0xc9,
+        /// Loads new firmware while SNP is initialized.
+        SNP_DOWNLOAD_FIRMWARE_EX = 0xca,
+        /// Commits the current firmware and TCB version.
+        SNP_COMMIT = 0xcb,
+        /// Loads a VLEK hashstick.
+        SNP_VLEK_LOAD = 0xcd,
+    }
+
+    /// Page types accepted by `SNP_LAUNCH_UPDATE` ("Page Types").
+    pub mod page_type: u8 {
+        /// A page of normal data.
+        NORMAL = 0x1,
+        /// A VMSA page.
+        VMSA = 0x2,
+        /// A page filled with zeroes.
+        ZERO = 0x3,
+        /// A page encrypted but not measured.
+        UNMEASURED = 0x4,
+        /// The secrets page.
+        SECRETS = 0x5,
+        /// The CPUID page.
+        CPUID = 0x6,
+    }
+
+    /// Bit positions within the guest policy ("Guest Policy Structure").
+    pub mod policy: u32 {
+        /// Lowest bit of the minimum ABI minor version.
+        ABI_MINOR = 0,
+        /// Lowest bit of the minimum ABI major version.
+        ABI_MAJOR = 8,
+        /// SMT is allowed.
+        SMT = 16,
+        /// Reserved, must be one.
+        RESERVED_MBO = 17,
+        /// Association with a migration agent is allowed.
+        MIGRATE_MA = 18,
+        /// Debugging is allowed.
+        DEBUG = 19,
+        /// The guest may only be activated on one socket.
+        SINGLE_SOCKET = 20,
+        /// CXL can be populated with devices or memory.
+        CXL_ALLOW = 21,
+        /// AES-256-XTS is required for memory encryption.
+        MEM_AES_256_XTS = 22,
+        /// Running Average Power Limit must be disabled.
+        RAPL_DIS = 23,
+        /// Ciphertext hiding must be enabled.
+        CIPHERTEXT_HIDING = 24,
+        /// Guest page swap commands are disallowed.
+        PAGE_SWAP_DISABLE = 25,
+    }
+
+    /// Guest message types ("Message Type Encodings").
+    pub mod message_type: u8 {
+        /// CPUID request.
+        MSG_CPUID_REQ = 1,
+        /// CPUID response.
+        MSG_CPUID_RSP = 2,
+        /// Key derivation request.
+        MSG_KEY_REQ = 3,
+        /// Key derivation response.
+        MSG_KEY_RSP = 4,
+        /// Attestation report request.
+        MSG_REPORT_REQ = 5,
+        /// Attestation report response.
+        MSG_REPORT_RSP = 6,
+        /// Guest export request.
+        MSG_EXPORT_REQ = 7,
+        /// Guest export response.
+        MSG_EXPORT_RSP = 8,
+        /// Guest import request.
+        MSG_IMPORT_REQ = 9,
+        /// Guest import response.
+        MSG_IMPORT_RSP = 10,
+        /// Guest absorb request.
+        MSG_ABSORB_REQ = 11,
+        /// Guest absorb response.
+        MSG_ABSORB_RSP = 12,
+        /// VMRK request.
+        MSG_VMRK_REQ = 13,
+        /// VMRK response.
+        MSG_VMRK_RSP = 14,
+        /// Guest absorb request without a migration agent.
+        MSG_ABSORB_NOMA_REQ = 15,
+        /// Guest absorb response without a migration agent.
+        MSG_ABSORB_NOMA_RSP = 16,
+        /// TSC information request.
+        MSG_TSC_INFO_REQ = 17,
+        /// TSC information response.
+        MSG_TSC_INFO_RSP = 18,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(command::name(0xa1), Some("SNP_LAUNCH_UPDATE"));
+        assert_eq!(command::name(0x00), None);
+        assert_eq!(page_type::name(page_type::CPUID), Some("CPUID"));
+        assert_eq!(page_type::name(0x7), None);
+        assert_eq!(policy::name(19), Some("DEBUG"));
+        assert_eq!(message_type::name(6), Some("MSG_REPORT_RSP"));
+    }
+}